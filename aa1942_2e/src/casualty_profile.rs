@@ -0,0 +1,81 @@
+use crate::*;
+use calc::Side;
+
+/// A named strategy for choosing casualties, from which a removal order is generated.
+///
+/// Every generated order takes hits on units which survive them, such as an undamaged
+/// battleship, before anything else, since those hits cost nothing.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CasualtyProfile {
+    /// The hand-tuned orders of `SurvivorSelector::default_attacker_order` and
+    /// `SurvivorSelector::default_defender_order`, which no generated profile reproduces.
+    #[default]
+    Standard,
+    /// Removes the cheapest units first, preserving IPC.
+    CheapestFirst,
+    /// Removes the weakest units of the side first, preserving strength in the current battle.
+    LowestStrengthFirst,
+    /// Removes the units with the lowest attack first, preserving attack power for later turns.
+    PreserveAttack,
+    /// Removes the units with the lowest defense first, preserving defense power for later
+    /// turns.
+    PreserveDefense,
+}
+
+impl CasualtyProfile {
+    pub fn all() -> [CasualtyProfile; 5] {
+        [
+            CasualtyProfile::Standard,
+            CasualtyProfile::CheapestFirst,
+            CasualtyProfile::LowestStrengthFirst,
+            CasualtyProfile::PreserveAttack,
+            CasualtyProfile::PreserveDefense,
+        ]
+    }
+
+    /// Returns the removal order of this profile for a force fighting for `side`. Ties are
+    /// broken by the other of IPC and strength.
+    pub fn removal_order(self, side: Side) -> Vec<Unit> {
+        use calc::Unit;
+
+        let key: fn(crate::Unit, Side) -> (u32, u32) = match self {
+            CasualtyProfile::Standard => {
+                return match side {
+                    Side::Attacker => SurvivorSelector::default_attacker_order(),
+                    Side::Defender => SurvivorSelector::default_defender_order(),
+                }
+            }
+            CasualtyProfile::CheapestFirst => |unit, side| (unit.ipc(), unit.strength(side) as u32),
+            CasualtyProfile::LowestStrengthFirst => {
+                |unit, side| (unit.strength(side) as u32, unit.ipc())
+            }
+            CasualtyProfile::PreserveAttack => |unit, _| (unit.attack() as u32, unit.ipc()),
+            CasualtyProfile::PreserveDefense => |unit, _| (unit.defense() as u32, unit.ipc()),
+        };
+
+        let mut order: Vec<crate::Unit> = crate::Unit::all()
+            .iter()
+            .copied()
+            .filter(|unit| unit.is_targetable())
+            .collect();
+        order.sort_by_key(|unit| (unit.damaged().is_none(), key(*unit, side)));
+        order
+    }
+}
+
+impl core::fmt::Display for CasualtyProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                CasualtyProfile::Standard => "Standard",
+                CasualtyProfile::CheapestFirst => "Cheapest First",
+                CasualtyProfile::LowestStrengthFirst => "Lowest Strength First",
+                CasualtyProfile::PreserveAttack => "Preserve Attack",
+                CasualtyProfile::PreserveDefense => "Preserve Defense",
+            }
+        )
+    }
+}
@@ -7,6 +7,9 @@ pub enum Hit {
     NotSubmarines,
     NotAirUnits,
     OnlyAirUnits,
+    /// Hits any unit except submarines, with the firing side choosing the casualty. Only rolled
+    /// under the house rule where the firing side chooses casualties.
+    Targeted,
 }
 
 impl calc::Hit<crate::Unit> for Hit {
@@ -17,16 +20,25 @@ impl calc::Hit<crate::Unit> for Hit {
                 Hit::NotSubmarines => !unit.is_submarine(),
                 Hit::NotAirUnits => !unit.is_air(),
                 Hit::OnlyAirUnits => unit.is_air(),
+                Hit::Targeted => !unit.is_submarine(),
             }
     }
+
+    fn allocator(self) -> calc::Allocator {
+        match self {
+            Hit::Targeted => calc::Allocator::Firer,
+            _ => calc::Allocator::Receiver,
+        }
+    }
 }
 
 impl Hit {
-    pub fn order() -> [Hit; 4] {
+    pub fn order() -> [Hit; 5] {
         [
             Hit::OnlyAirUnits,
             Hit::NotAirUnits,
             Hit::NotSubmarines,
+            Hit::Targeted,
             Hit::AllUnits,
         ]
     }
@@ -1,46 +1,143 @@
 mod battle_phase;
+mod casualty_profile;
 mod hit;
+mod optimal_survivor_selector;
 mod roll_selector;
 mod survivor_selector;
 mod unit;
 
 pub use crate::stats::*;
 pub use battle_phase::BattlePhase;
+pub use casualty_profile::CasualtyProfile;
 pub use hit::Hit;
+pub use optimal_survivor_selector::{Objective, OptimalSurvivorSelector};
 pub use roll_selector::RollSelector;
-pub use survivor_selector::SurvivorSelector;
+pub use survivor_selector::{Reservation, SurvivorSelector};
 pub use unit::Unit;
 
 use calc::*;
-pub fn get_combat_manager() -> CombatManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector>
-{
-    let attacker_survivor_selector = SurvivorSelector {
-        removal_order: SurvivorSelector::default_attacker_order(),
-        reserved: Some(Unit::Tank),
-    };
-    let defender_survivor_selector = SurvivorSelector {
-        removal_order: SurvivorSelector::default_defender_order(),
-        reserved: None,
-    };
+pub fn get_combat_manager<TProb: ProbValue>(
+) -> CombatManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb> {
+    get_combat_manager_with(RollSelector::default())
+}
+
+pub fn get_combat_manager_with<TProb: ProbValue>(
+    roll_selector: RollSelector,
+) -> CombatManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb> {
+    get_combat_manager_reserving(
+        roll_selector,
+        SurvivorSelector::default_attacker_reservations(),
+        Vec::new(),
+    )
+}
+
+/// Constructs a `CombatManager` where each side keeps its reserved units for as long as it can.
+pub fn get_combat_manager_reserving<TProb: ProbValue>(
+    roll_selector: RollSelector,
+    attacker_reserved: Vec<Reservation>,
+    defender_reserved: Vec<Reservation>,
+) -> CombatManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb> {
+    get_combat_manager_selecting(
+        roll_selector,
+        SurvivorSelector::from_profile(
+            CasualtyProfile::Standard,
+            Side::Attacker,
+            attacker_reserved,
+        ),
+        SurvivorSelector::from_profile(
+            CasualtyProfile::Standard,
+            Side::Defender,
+            defender_reserved,
+        ),
+    )
+}
 
-    let roll_selector = RollSelector {};
-    CombatManager::new(
+/// Constructs a `CombatManager` where each side chooses its casualties with the given selector.
+pub fn get_combat_manager_selecting<TProb: ProbValue>(
+    roll_selector: RollSelector,
+    attacker_survivor_selector: SurvivorSelector,
+    defender_survivor_selector: SurvivorSelector,
+) -> CombatManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb> {
+    let mut combat_manager = CombatManager::new(
         attacker_survivor_selector,
         defender_survivor_selector,
         roll_selector,
-    )
+    );
+    let target_selector = || SurvivorSelector {
+        removal_order: SurvivorSelector::most_expensive_order(),
+        reserved: Vec::new(),
+    };
+    combat_manager.set_target_selectors(target_selector(), target_selector());
+    combat_manager
+}
+
+/// Constructs a `CombatManager` where both sides choose the casualties best serving `objective`,
+/// looking ahead the given number of game rounds. Hits allocated by the firing side are
+/// allocated in pursuit of the objective for the firing side.
+pub fn get_optimal_combat_manager_with<TProb: ProbValue>(
+    roll_selector: RollSelector,
+    objective: Objective,
+    lookahead: usize,
+) -> CombatManager<BattlePhase, Unit, Hit, RollSelector, OptimalSurvivorSelector, TProb> {
+    let selector = |removal_order, reserved| {
+        let fallback = SurvivorSelector {
+            removal_order,
+            reserved,
+        };
+        OptimalSurvivorSelector::new(objective, lookahead, roll_selector, fallback)
+    };
+    let target_selector = || {
+        let fallback = SurvivorSelector {
+            removal_order: SurvivorSelector::most_expensive_order(),
+            reserved: Vec::new(),
+        };
+        OptimalSurvivorSelector::targeting(objective, lookahead, roll_selector, fallback)
+    };
+
+    let mut combat_manager = CombatManager::new(
+        selector(
+            SurvivorSelector::default_attacker_order(),
+            SurvivorSelector::default_attacker_reservations(),
+        ),
+        selector(SurvivorSelector::default_defender_order(), Vec::new()),
+        roll_selector,
+    );
+    combat_manager.set_target_selectors(target_selector(), target_selector());
+    combat_manager
 }
 
-pub fn create_round_manager(
+pub fn create_round_manager<TProb: ProbValue>(
     attackers: Force<Unit>,
     defenders: Force<Unit>,
-) -> RoundManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector> {
+) -> RoundManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb> {
     let sequence = BattlePhase::create_sequence(&attackers, &defenders);
     let combat_manager = get_combat_manager();
 
     RoundManager::new(combat_manager, sequence.clone(), attackers, defenders)
 }
 
+pub fn create_markov_solver<TProb: ProbValue>(
+    attackers: Force<Unit>,
+    defenders: Force<Unit>,
+) -> MarkovSolver<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb> {
+    let sequence = BattlePhase::create_sequence(&attackers, &defenders);
+    let combat_manager = get_combat_manager();
+
+    MarkovSolver::new(combat_manager, sequence, attackers, defenders)
+}
+
+pub fn create_simulator<TProb: ProbValue>(
+    attackers: Force<Unit>,
+    defenders: Force<Unit>,
+    trials: u32,
+    seed: u64,
+) -> Simulator<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb> {
+    let sequence = BattlePhase::create_sequence(&attackers, &defenders);
+    let combat_manager = get_combat_manager();
+
+    Simulator::new(combat_manager, sequence, attackers, defenders, trials, seed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -201,11 +298,36 @@ mod tests {
         assert_eq!(summary.attacker.win_p, Probability::zero());
         assert_eq!(summary.defender.win_p, Probability::zero());
         assert_eq!(summary.draw_p, Probability::zero());
-        assert_eq!(
-            round_manager.last_round().total_probability(),
-            Probability::one()
-        );
-        assert!(round_manager.last_round().stalemate);
+        assert_eq!(summary.stalemate_p, Probability::one());
+        assert_eq!(summary.stalemated_combats.len(), 1);
+        assert!(assert_prob_eq(summary.total_p, Probability::one(), 1));
+        // The stalemate is detected as soon as the first cycle ends.
+        assert_eq!(summary.round_count(), 2);
+    }
+
+    #[test]
+    fn round_limit_is_not_stalemate() {
+        let attackers = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_round_limit(1);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // The combats still pending after the first round are cut off, not stalemated.
+        assert_eq!(summary.stalemate_p, Probability::zero());
+        assert!(summary.stalemated_combats.is_empty());
+        assert!(assert_prob_eq(
+            summary.pruned_p,
+            Probability::from_ratio(5, 9),
+            2
+        ));
+        assert!(assert_prob_eq(
+            summary.total_p,
+            Probability::from_ratio(4, 9),
+            2
+        ));
     }
 
     #[test]
@@ -215,7 +337,7 @@ mod tests {
             Force::new(vec![Quant::new(Unit::Fighter, 1), Quant::new(Unit::Destroyer, 1)].into());
 
         let (mut summarizer, mut round_manager) = setup(attackers, defenders);
-        let last_round = run_to_completion(&mut round_manager, &mut summarizer);
+        run_to_completion(&mut round_manager, &mut summarizer);
         let summary = summarizer.summarize();
 
         // See test_probabilities.txt for probabilty calculations
@@ -227,11 +349,16 @@ mod tests {
         ));
         assert_eq!(summary.draw_p, Probability::zero());
         assert!(assert_prob_eq(
-            last_round.total_probability(),
+            summary.stalemate_p,
             Probability::from_ratio(845, 1679),
             2
         ));
-        assert!(round_manager.last_round().stalemate);
+        // Only the combats where the destroyer was sunk are stalemated.
+        for combat in summary.stalemated_combats.outcomes() {
+            assert_eq!(combat.item.defenders.count(&Unit::Destroyer), 0);
+            assert_eq!(combat.item.defenders.count(&Unit::Fighter), 1);
+        }
+        assert!(assert_prob_eq(summary.total_p, Probability::one(), 2));
     }
 
     #[test]
@@ -399,29 +526,1603 @@ mod tests {
         assert!(!round_manager.last_round().stalemate);
     }
 
-    fn setup(
-        attackers: Force<Unit>,
-        defenders: Force<Unit>,
-    ) -> (
-        Summarizer<BattlePhase, Unit>,
-        RoundManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector>,
-    ) {
-        let sequence = BattlePhase::create_sequence(&attackers, &defenders);
-        let combat_manager = get_combat_manager();
+    #[test]
+    fn reservations() {
+        let selector = SurvivorSelector {
+            removal_order: SurvivorSelector::default_attacker_order(),
+            reserved: vec![
+                Reservation::new(Unit::Tank, 1, 1),
+                Reservation::new(Unit::Infantry, 2, 0),
+            ],
+        };
+        let force: QuantDist<Unit> = vec![
+            Quant::new(Unit::Infantry, 3),
+            Quant::new(Unit::Tank, 1),
+            Quant::new(Unit::Fighter, 1),
+        ]
+        .into();
+        let survivors = |hit_count| {
+            let hits: QuantDist<Hit> = vec![Quant::new(Hit::AllUnits, hit_count)].into();
+            let hits: ProbDist<_, Probability> = vec![Prob::new(hits, Probability::one())].into();
+            let survivors = calc::SurvivorSelector::select(&selector, &force, &hits);
+            let survivors = &survivors.outcomes()[0].item;
+            (
+                survivors.count(&Unit::Infantry),
+                survivors.count(&Unit::Tank),
+                survivors.count(&Unit::Fighter),
+            )
+        };
 
-        let mut round_manager =
-            RoundManager::new(combat_manager, sequence.clone(), attackers, defenders);
-        round_manager.set_prune_threshold(Probability::zero());
+        // Unreserved units are taken first, despite the removal order
+        assert_eq!(survivors(1), (2, 1, 1));
+        assert_eq!(survivors(2), (2, 1, 0));
+        // Then the reservations are released, lowest priority first
+        assert_eq!(survivors(3), (1, 1, 0));
+        assert_eq!(survivors(4), (0, 1, 0));
+        assert_eq!(survivors(5), (0, 0, 0));
+    }
+
+    #[test]
+    fn casualty_profiles() {
+        use calc::Unit as _;
+
+        // The standard profile keeps the original removal orders.
+        assert_eq!(
+            CasualtyProfile::Standard.removal_order(Side::Attacker),
+            vec![
+                Unit::Battleship,
+                Unit::Infantry,
+                Unit::Artillery,
+                Unit::Tank,
+                Unit::Submarine,
+                Unit::Destroyer,
+                Unit::Fighter,
+                Unit::Bomber,
+                Unit::Cruiser,
+                Unit::Carrier,
+                Unit::BattleshipDamaged,
+                Unit::AntiAir,
+            ]
+        );
+        assert_eq!(
+            CasualtyProfile::Standard.removal_order(Side::Defender),
+            vec![
+                Unit::Battleship,
+                Unit::Infantry,
+                Unit::Artillery,
+                Unit::AntiAir,
+                Unit::Tank,
+                Unit::Submarine,
+                Unit::Destroyer,
+                Unit::Bomber,
+                Unit::Fighter,
+                Unit::Cruiser,
+                Unit::Carrier,
+                Unit::BattleshipDamaged,
+            ]
+        );
+
+        // A battleship absorbs its first hit for free, then ties in IPC go to the weaker unit
+        let order = CasualtyProfile::CheapestFirst.removal_order(Side::Attacker);
+        assert_eq!(
+            order[..6],
+            [
+                Unit::Battleship,
+                Unit::Infantry,
+                Unit::Artillery,
+                Unit::AntiAir,
+                Unit::Submarine,
+                Unit::Tank,
+            ]
+        );
+        assert!(!order.contains(&Unit::BombardingBattleship));
+
+        let order = CasualtyProfile::PreserveDefense.removal_order(Side::Attacker);
+        assert_eq!(order.len(), 12);
+        assert!(order[1..]
+            .windows(2)
+            .all(|pair| pair[0].defense() <= pair[1].defense()));
+    }
+
+    #[test]
+    fn retreat_after_rounds() {
+        let attackers = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_retreat_policy(RetreatAfterRounds { rounds: 1 });
+        let last_round = run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        assert!(assert_prob_eq(
+            summary.attacker.win_p,
+            Probability::from_ratio(4, 36),
+            2
+        ));
+        assert!(assert_prob_eq(
+            summary.defender.win_p,
+            Probability::from_ratio(10, 36),
+            1
+        ));
+        assert!(assert_prob_eq(
+            summary.draw_p,
+            Probability::from_ratio(2, 36),
+            1
+        ));
+        assert!(assert_prob_eq(
+            summary.retreat_p,
+            Probability::from_ratio(20, 36),
+            1
+        ));
+        assert_eq!(summary.retreated_combats.len(), 1);
+        assert!(assert_prob_eq(summary.total_p, Probability::one(), 1));
+        assert_eq!(last_round.total_probability(), Probability::zero());
+        assert!(!round_manager.last_round().stalemate);
+    }
+
+    #[test]
+    fn retreat_closure() {
+        let attackers = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_retreat_policy(
+            |_: &Combat<BattlePhase, Unit>, rounds_completed: usize| rounds_completed >= 2,
+        );
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // Both rounds miss on both sides with 20/36 each.
+        assert!(assert_prob_eq(
+            summary.retreat_p,
+            Probability::from_ratio(400, 1296),
+            4
+        ));
+        assert!(assert_prob_eq(summary.total_p, Probability::one(), 2));
+    }
+
+    #[test]
+    fn retreat_below_ipc() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 2)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Tank, 2)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_retreat_policy(RetreatBelowIpc { ipc: 12 });
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // Any pending combat where an attacking tank has been lost is retreated from.
+        for combat in summary.retreated_combats.outcomes() {
+            assert_eq!(combat.item.attackers.count(&Unit::Tank), 1);
+            assert!(!combat.item.defenders.is_empty());
+        }
+        assert!(summary.retreat_p > Probability::zero());
+        assert!(assert_prob_eq(
+            summary.attacker.win_p + summary.defender.win_p + summary.draw_p + summary.retreat_p,
+            Probability::one(),
+            4
+        ));
+    }
+
+    #[test]
+    fn prune_top_k() {
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Infantry, 6), Quant::new(Unit::Tank, 3)].into());
+        let defenders = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 6),
+                Quant::new(Unit::Artillery, 2),
+            ]
+            .into(),
+        );
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_pruning_strategy(TopK { k: 5 });
+        while !round_manager.is_complete() {
+            let round = round_manager.advance_round();
+            assert!(round.pending.len() <= 5);
+            summarizer.add_round(round);
+        }
+        let summary = summarizer.summarize();
+
+        assert!(summary.pruned_p > Probability::zero());
+        assert!(assert_prob_eq(
+            summary.total_p + summary.pruned_p,
+            Probability::one(),
+            4
+        ));
+    }
+
+    #[test]
+    fn prune_mass_budget() {
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Infantry, 6), Quant::new(Unit::Tank, 3)].into());
+        let defenders = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 6),
+                Quant::new(Unit::Artillery, 2),
+            ]
+            .into(),
+        );
+        let budget = Probability::new(0.001);
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_pruning_strategy(MassBudget { budget });
+        while !round_manager.is_complete() {
+            let round = round_manager.advance_round();
+            assert!(round.pruned_p <= budget);
+            summarizer.add_round(round);
+        }
+        let summary = summarizer.summarize();
+
+        assert!(summary.pruned_p > Probability::zero());
+        assert!(assert_prob_eq(
+            summary.total_p + summary.pruned_p,
+            Probability::one(),
+            4
+        ));
+    }
+
+    #[test]
+    fn prune_adaptive() {
+        let mut strategy = AdaptiveThreshold::new(Probability::zero(), 4);
+        let pending: Vec<Probability> = [2, 3, 4, 5, 86]
+            .iter()
+            .map(|n| Probability::from_ratio(*n, 100))
+            .collect();
+
+        // Too many pending combats raises the threshold tenfold each round.
+        assert_eq!(strategy.prune_count(&pending), 0);
+        assert_eq!(
+            strategy.threshold(),
+            Probability::from_ratio(1, 1_000_000_000)
+        );
+        for _ in 0..7 {
+            assert_eq!(strategy.prune_count(&pending), 0);
+        }
+        assert_eq!(strategy.prune_count(&pending), 4);
+        assert!(assert_prob_eq(
+            strategy.threshold(),
+            Probability::from_ratio(1, 10),
+            4
+        ));
+
+        // Few pending combats lowers it again, down to the initial threshold.
+        strategy.prune_count(&[]);
+        assert!(assert_prob_eq(
+            strategy.threshold(),
+            Probability::from_ratio(1, 100),
+            4
+        ));
+        for _ in 0..10 {
+            strategy.prune_count(&[]);
+        }
+        assert_eq!(strategy.threshold(), Probability::zero());
+    }
+
+    #[test]
+    fn pending_bounds() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        // After the first round, the third of the battle still pending could end either way.
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        summarizer.add_round(round_manager.advance_round());
+        let summary = summarizer.summarize();
+        assert_eq!(summary.pruned_p, Probability::zero());
+        let bounds = summary.attacker_win_bounds();
+        assert!(assert_prob_eq(
+            bounds.lower,
+            Probability::from_ratio(1, 3),
+            4
+        ));
+        assert!(assert_prob_eq(
+            bounds.upper,
+            Probability::from_ratio(2, 3),
+            4
+        ));
+    }
+
+    #[test]
+    fn prune_bounds() {
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Infantry, 6), Quant::new(Unit::Tank, 3)].into());
+        let defenders = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 6),
+                Quant::new(Unit::Artillery, 2),
+            ]
+            .into(),
+        );
+
+        let (mut summarizer, mut round_manager) = setup(attackers.clone(), defenders.clone());
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let exact = summarizer.summarize();
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_prune_threshold(Probability::new(0.001));
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let pruned = summarizer.summarize();
+
+        assert!(pruned.pruned_p > Probability::zero());
+        assert!(pruned.attacker_win_bounds().contains(&exact.attacker.win_p));
+        assert!(pruned.defender_win_bounds().contains(&exact.defender.win_p));
+        assert!(pruned.draw_bounds().contains(&exact.draw_p));
+        assert!(assert_prob_eq(
+            pruned.attacker_win_bounds().width(),
+            pruned.pruned_p,
+            4
+        ));
+
+        let renormalized = pruned.renormalized();
+        assert_eq!(renormalized.pruned_p, Probability::zero());
+        assert!(assert_prob_eq(
+            renormalized.attacker.win_p + renormalized.defender.win_p + renormalized.draw_p,
+            Probability::one(),
+            4
+        ));
+        assert!(renormalized.attacker.win_p > pruned.attacker.win_p);
+    }
+
+    #[test]
+    fn outcome_distributions() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let solution = create_markov_solver::<ExactProbability>(attackers, defenders).solve();
+        let summary = solution.summarize();
+
+        // Each round, the tank survives and wins with 1/3, is lost with 1/3, and otherwise the
+        // round repeats. So the tank is lost, along with its 6 IPC, half the time.
+        let attacker = &summary.attacker;
+        let survives = ExactProbability::from_ratio(1, 2);
+        assert_eq!(attacker.ipc_lost_dist.len(), 2);
+        assert_eq!(attacker.ipc_lost_dist.cdf(&0), survives);
+        assert_eq!(attacker.ipc_lost_over_p(0), survives);
+        assert_eq!(attacker.ipc_lost_over_p(6), ExactProbability::zero());
+        assert_eq!(
+            attacker.ipc_lost_percentile(&ExactProbability::from_ratio(1, 2)),
+            0
+        );
+        assert_eq!(
+            attacker.ipc_lost_percentile(&ExactProbability::from_ratio(9, 10)),
+            6
+        );
+        assert_eq!(attacker.ipc_dist.outcomes()[0].item, 0);
+        assert_eq!(attacker.ipc_dist.outcomes()[1].item, 6);
+        assert_eq!(attacker.unit_count_dist.median(), Some(&0));
+
+        let mean: f64 = attacker
+            .ipc_dist
+            .outcomes()
+            .iter()
+            .map(|o| o.item as f64 * o.p.to_f64())
+            .sum();
+        assert!(approx_eq!(f64, mean, attacker.ipc.mean, ulps = 4));
+    }
+
+    #[test]
+    fn unit_survival() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let solution = create_markov_solver::<ExactProbability>(attackers, defenders).solve();
+        let summary = solution.summarize();
+
+        // The tank survives half the time. The infantry only survives when the tank misses and
+        // the infantry hits, which happens 1/6 of the time each round, out of 2/3 that end it.
+        let tank = summary.unit_survival(Side::Attacker, &Unit::Tank);
+        assert_eq!(tank.survival_p(), ExactProbability::from_ratio(1, 2));
+        assert_eq!(tank.at_least_p(0), ExactProbability::one());
+        assert_eq!(tank.at_least_p(2), ExactProbability::zero());
+        assert!(approx_eq!(f64, tank.count.mean, 0.5, ulps = 2));
+
+        let infantry = summary.unit_survival(Side::Defender, &Unit::Infantry);
+        assert_eq!(infantry.survival_p(), ExactProbability::from_ratio(1, 4));
+        assert_eq!(infantry.count_dist.len(), 2);
+
+        let survivals = summary.unit_survivals(Side::Attacker);
+        assert_eq!(survivals.len(), 1);
+        assert_eq!(survivals[0].0, Unit::Tank);
+        assert_eq!(survivals[0].1, tank);
+
+        let bomber = summary.unit_survival(Side::Attacker, &Unit::Bomber);
+        assert_eq!(bomber.survival_p(), ExactProbability::zero());
+    }
+
+    #[test]
+    fn unit_survivals_include_lost_units() {
+        let attackers = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 1),
+                Quant::new(Unit::BombardingCruiser, 1),
+            ]
+            .into(),
+        );
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // The cruiser only bombards, so it's never among the survivors.
+        let survivals = summary.unit_survivals(Side::Attacker);
+        let units: Vec<_> = survivals.iter().map(|(unit, _)| *unit).collect();
+        assert_eq!(units, vec![Unit::Infantry, Unit::BombardingCruiser]);
+        assert_eq!(survivals[1].1.survival_p(), Probability::zero());
+    }
+
+    #[test]
+    fn conditional_summary_unresolved() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        // The first round resolves two thirds of the battle, half of which are attacker wins.
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        summarizer.add_round(round_manager.advance_round());
+        let wins = summarizer.summarize_where(Side::Attacker, |combat| {
+            combat.winner() == Some(Side::Attacker)
+        });
+        assert!(assert_prob_eq(wins.p, Probability::from_ratio(1, 2), 4));
+    }
+
+    #[test]
+    fn conditional_summary() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        run_to_completion(&mut round_manager, &mut summarizer);
+
+        // The tank always survives when the attacker wins.
+        let wins = summarizer.summarize_where(Side::Attacker, |combat| {
+            combat.winner() == Some(Side::Attacker)
+        });
+        assert!(assert_prob_eq(wins.p, Probability::from_ratio(1, 2), 4));
+        assert!(assert_prob_eq(wins.summary.win_p, Probability::one(), 4));
+        assert!(approx_eq!(
+            f64,
+            wins.summary.ipc_lost.mean,
+            0.0,
+            epsilon = 1e-9
+        ));
+
+        // The infantry survives half the time the defender holds.
+        let holds = summarizer.summarize_where(Side::Defender, |combat| {
+            combat.winner() != Some(Side::Attacker)
+        });
+        assert!(assert_prob_eq(holds.p, Probability::from_ratio(1, 2), 4));
+        assert!(approx_eq!(
+            f64,
+            holds.summary.unit_count.mean,
+            0.5,
+            epsilon = 1e-9
+        ));
+        // The variances are conditioned on the defender holding too: the infantry's survival is
+        // a fair coin flip, so its IPC (3) varies by 3² × 0.25.
+        assert!(approx_eq!(
+            f64,
+            holds.summary.unit_count.variance,
+            0.25,
+            epsilon = 1e-9
+        ));
+        assert!(approx_eq!(
+            f64,
+            holds.summary.ipc.variance,
+            2.25,
+            epsilon = 1e-9
+        ));
+        assert!(approx_eq!(
+            f64,
+            holds.summary.ipc_lost.variance,
+            2.25,
+            epsilon = 1e-9
+        ));
+        assert!(assert_prob_eq(
+            holds.summary.win_p,
+            Probability::from_ratio(1, 2),
+            4
+        ));
+
+        let none = summarizer.summarize_where(Side::Attacker, |_| false);
+        assert_eq!(none.p, Probability::zero());
+    }
+
+    #[test]
+    fn capture() {
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Infantry, 1), Quant::new(Unit::Fighter, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // Whenever only the fighter survives, the territory is cleared but not captured.
+        let fighter_only: Probability = summary
+            .completed_combats
+            .outcomes()
+            .iter()
+            .filter(|combat| {
+                combat.item.winner() == Some(Side::Attacker)
+                    && combat.item.attackers.count(&Unit::Infantry) == 0
+            })
+            .map(|combat| combat.p)
+            .sum();
+        assert!(fighter_only > Probability::zero());
+        assert!(assert_prob_eq(
+            summary.capture_p + fighter_only,
+            summary.attacker.win_p,
+            4
+        ));
+    }
+
+    #[test]
+    fn phase_casualties() {
+        let attackers = Force::new(vec![Quant::new(Unit::Fighter, 2)].into());
+        let defenders =
+            Force::new(vec![Quant::new(Unit::AntiAir, 1), Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // Each fighter is shot down by the AA gun with a probability of 1/6.
+        let anti_air = &summary.casualties[&BattlePhase::AntiAir];
+        assert!((anti_air.attackers[&Unit::Fighter] - 1.0 / 3.0).abs() < 1e-9);
+        assert!(anti_air.defenders.is_empty());
+        assert!(
+            (summary.casualty_ipc(BattlePhase::AntiAir, Side::Attacker) - 10.0 / 3.0).abs() < 1e-9
+        );
+
+        // Every fighter lost is lost in some phase.
+        let fighters_lost: f64 = summary
+            .casualties
+            .values()
+            .filter_map(|casualties| casualties.attackers.get(&Unit::Fighter))
+            .sum();
+        let survival = summary.unit_survival(Side::Attacker, &Unit::Fighter);
+        assert!((fighters_lost - (2.0 - survival.count.mean)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn pruned_casualties() {
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Infantry, 3), Quant::new(Unit::Tank, 2)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 4)].into());
+
+        // Only the casualties of the outcomes which were kept are counted.
+        let assert_kept_casualties = |round: &RoundResult<BattlePhase, Unit>| {
+            assert!(round.pruned_p > Probability::zero());
+            for (side, force) in &[(Side::Attacker, &attackers), (Side::Defender, &defenders)] {
+                for quant in force.outcomes() {
+                    let casualties: f64 = round
+                        .casualties
+                        .values()
+                        .filter_map(|casualties| casualties.side(*side).get(&quant.item))
+                        .sum();
+                    let expected: f64 = round
+                        .completed
+                        .outcomes()
+                        .iter()
+                        .chain(round.pending.outcomes())
+                        .map(|combat| {
+                            let survivors = CombatContext::from_combat(&combat.item, *side)
+                                .friendlies()
+                                .count(&quant.item);
+                            (quant.count - survivors) as f64 * combat.p.to_f64()
+                        })
+                        .sum();
+                    assert!((casualties - expected).abs() < 1e-9);
+                }
+            }
+        };
+
+        let (_, mut round_manager) = setup(attackers.clone(), defenders.clone());
+        round_manager.set_pruning_strategy(TopK { k: 3 });
+        assert_kept_casualties(round_manager.advance_round());
+
+        let (_, mut round_manager) = setup(attackers.clone(), defenders.clone());
+        round_manager.set_prune_threshold(Probability::new(0.01));
+        assert_kept_casualties(round_manager.advance_round());
+    }
+
+    #[test]
+    fn most_likely_path() {
+        let attackers = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_track_predecessors(true);
+        let initial = round_manager.last_round().pending.outcomes()[0]
+            .item
+            .clone();
+        run_to_completion(&mut round_manager, &mut summarizer);
+
+        // The defenders most likely win in the first round, where the attacker misses and the
+        // defender hits with a probability of 5/6 * 2/6.
+        let paths = summarizer.most_likely_paths(2);
+        assert_eq!(paths.len(), 2);
+        let path = &paths[0];
+        assert_eq!(path.steps.len(), 2);
+        assert_eq!(path.steps[0].item, initial);
+        assert_eq!(path.steps[0].p, Probability::one());
+        assert_eq!(path.last().winner(), Some(Side::Defender));
+        assert!(assert_prob_eq(path.p, Probability::from_ratio(10, 36), 4));
+        assert!(assert_prob_eq(path.steps[1].p, path.p, 4));
+
+        // The next most likely outcome is the attackers winning, also in the first round.
+        assert_eq!(paths[1].last().winner(), Some(Side::Attacker));
+        assert!(assert_prob_eq(
+            paths[1].p,
+            Probability::from_ratio(4, 36),
+            4
+        ));
+    }
+
+    #[test]
+    fn capture_anti_air() {
+        let combat = Combat {
+            battle_phase: BattlePhase::General,
+            attackers: Force::new(vec![Quant::new(Unit::AntiAir, 1)].into()),
+            defenders: Force::new(QuantDist::default()),
+        };
+
+        // Anti-air guns never capture a territory on their own.
+        assert_eq!(combat.winner(), Some(Side::Attacker));
+        assert!(!combat.captured());
+    }
+
+    #[test]
+    fn capture_naval() {
+        let attackers = Force::new(vec![Quant::new(Unit::Battleship, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Destroyer, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        assert!(summary.attacker.win_p > Probability::zero());
+        assert_eq!(summary.capture_p, Probability::zero());
+    }
+
+    #[test]
+    fn tuv_swing() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let solution = create_markov_solver::<ExactProbability>(attackers, defenders).solve();
+        let summary = solution.summarize();
+
+        // The tank wins with 1/2 for a swing of 3, trades with 1/4 for a swing of -3, and is
+        // lost with 1/4 for a swing of -6.
+        let swing = &summary.tuv_swing;
+        let outcomes: Vec<_> = swing
+            .swing_dist
+            .outcomes()
+            .iter()
+            .map(|o| (o.item, o.p.clone()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (-6, ExactProbability::from_ratio(1, 4)),
+                (-3, ExactProbability::from_ratio(1, 4)),
+                (3, ExactProbability::from_ratio(1, 2)),
+            ]
+        );
+        assert_eq!(swing.positive_p(), ExactProbability::from_ratio(1, 2));
+        assert!(approx_eq!(f64, swing.swing.mean, -0.75, ulps = 4));
+        assert!(approx_eq!(f64, swing.ipc_lost_covariance, -2.25, ulps = 4));
+
+        let attacker = summary.attacker.ipc_lost.variance;
+        let defender = summary.defender.ipc_lost.variance;
+        assert!(approx_eq!(
+            f64,
+            swing.swing.variance,
+            attacker + defender - 2.0 * swing.ipc_lost_covariance,
+            ulps = 4
+        ));
+    }
+
+    #[test]
+    fn tuv_swing_pruned() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        // The first round ends the same ways as the whole battle, with a third left pending.
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_round_limit(1);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+        assert!(assert_prob_eq(
+            summary.pruned_p,
+            Probability::from_ratio(1, 3),
+            2
+        ));
+
+        let covariance = summary.tuv_swing.ipc_lost_covariance;
+        assert!(approx_eq!(f64, covariance, -2.25, ulps = 4));
+        let renormalized = summary.renormalized();
+        assert!(approx_eq!(
+            f64,
+            renormalized.tuv_swing.ipc_lost_covariance,
+            covariance,
+            ulps = 4
+        ));
+
+        // The swing and the IPC lost by each side are normalized the same way as the
+        // covariance, even when much of the battle is pruned.
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Infantry, 6), Quant::new(Unit::Tank, 3)].into());
+        let defenders = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 6),
+                Quant::new(Unit::Artillery, 2),
+            ]
+            .into(),
+        );
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_pruning_strategy(TopK { k: 5 });
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+        assert!(summary.pruned_p > Probability::new(0.1));
+
+        for summary in &[summary.clone(), summary.renormalized()] {
+            let tuv_swing = &summary.tuv_swing;
+            assert!(approx_eq!(
+                f64,
+                tuv_swing.swing.variance,
+                summary.attacker.ipc_lost.variance + summary.defender.ipc_lost.variance
+                    - 2.0 * tuv_swing.ipc_lost_covariance,
+                epsilon = 1e-9
+            ));
+        }
+    }
+
+    #[test]
+    fn luck() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let won = Combat {
+            battle_phase: BattlePhase::General,
+            attackers: attackers.clone(),
+            defenders: Force::new(QuantDist::default()),
+        };
+        let traded = Combat {
+            battle_phase: BattlePhase::General,
+            attackers: Force::new(QuantDist::default()),
+            defenders: Force::new(QuantDist::default()),
+        };
+
+        let solution = create_markov_solver::<ExactProbability>(attackers, defenders).solve();
+        let summary = solution.summarize();
+
+        // The swings are -6 with 1/4, -3 with 1/4, and 3 with 1/2.
+        let luck = summary.luck(&won, Side::Attacker);
+        assert_eq!(luck.swing, 3);
+        assert_eq!(luck.percentile, ExactProbability::from_ratio(3, 4));
+        assert_eq!(luck.at_least_p, ExactProbability::from_ratio(1, 2));
+
+        let luck = summary.luck(&won, Side::Defender);
+        assert_eq!(luck.swing, -3);
+        assert_eq!(luck.percentile, ExactProbability::from_ratio(1, 4));
+        assert_eq!(luck.at_least_p, ExactProbability::one());
+
+        let luck = summary.luck(&traded, Side::Attacker);
+        assert_eq!(luck.swing, -3);
+        assert_eq!(luck.percentile, ExactProbability::from_ratio(3, 8));
+        assert_eq!(luck.at_least_p, ExactProbability::from_ratio(3, 4));
+    }
+
+    #[test]
+    fn luck_with_retreats() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let retreated = Combat {
+            battle_phase: BattlePhase::General,
+            attackers: attackers.clone(),
+            defenders: defenders.clone(),
+        };
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        round_manager.set_retreat_policy(RetreatAfterRounds { rounds: 1 });
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // The swings are -6 with 1/6, -3 with 1/6, 0 with 1/3 from retreating, and 3 with 1/3.
+        let luck = summary.luck(&retreated, Side::Attacker);
+        assert_eq!(luck.swing, 0);
+        assert!(assert_prob_eq(
+            luck.percentile,
+            Probability::from_ratio(1, 2),
+            4
+        ));
+        assert!(assert_prob_eq(
+            luck.at_least_p,
+            Probability::from_ratio(2, 3),
+            4
+        ));
+    }
+
+    #[test]
+    #[cfg(feature = "serde1")]
+    fn checkpoint_resume() {
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Infantry, 3), Quant::new(Unit::Tank, 2)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 4)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers.clone(), defenders.clone());
+        round_manager.set_prune_threshold(Probability::from_ratio(1, 1_000_000));
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let expected = summarizer.summarize();
+
+        let (mut summarizer, mut round_manager) = setup(attackers.clone(), defenders.clone());
+        round_manager.set_prune_threshold(Probability::from_ratio(1, 1_000_000));
+        for _ in 0..2 {
+            summarizer.add_round(round_manager.advance_round());
+        }
+        let manager_json = serde_json::to_string(&round_manager.checkpoint()).unwrap();
+        let summarizer_json = serde_json::to_string(&summarizer.checkpoint()).unwrap();
+        drop(round_manager);
+        drop(summarizer);
+
+        let sequence = BattlePhase::create_sequence(&attackers, &defenders);
+        let checkpoint = serde_json::from_str(&manager_json).unwrap();
+        let mut round_manager =
+            RoundManager::resume(get_combat_manager(), sequence, checkpoint).unwrap();
+        let checkpoint = serde_json::from_str(&summarizer_json).unwrap();
+        let mut summarizer = Summarizer::resume(checkpoint).unwrap();
+        assert_eq!(round_manager.round_index(), 2);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        assert_eq!(summary.round_count(), expected.round_count());
+        assert!(assert_prob_eq(
+            summary.attacker.win_p,
+            expected.attacker.win_p,
+            16
+        ));
+        assert!(assert_prob_eq(
+            summary.defender.win_p,
+            expected.defender.win_p,
+            16
+        ));
+        assert!(assert_prob_eq(summary.pruned_p, expected.pruned_p, 16));
+
+        // A checkpoint saved in another version of the format can't be resumed.
+        let checkpoint = summarizer_json.replacen(
+            &format!("\"version\":{}", CHECKPOINT_VERSION),
+            "\"version\":0",
+            1,
+        );
+        let old: SummarizerCheckpoint<BattlePhase, Unit> =
+            serde_json::from_str(&checkpoint).unwrap();
+        assert_eq!(
+            Summarizer::resume(old).unwrap_err(),
+            CheckpointError::UnsupportedVersion(0)
+        );
+
+        // Even if the rest of it can't be deserialized, the version can be checked.
+        let checkpoint = r#"{"version":0,"summarizer":{"rounds":[]}}"#;
+        assert!(
+            serde_json::from_str::<SummarizerCheckpoint<BattlePhase, Unit>>(checkpoint).is_err()
+        );
+        let version: CheckpointVersion = serde_json::from_str(checkpoint).unwrap();
+        assert_eq!(
+            version.check().unwrap_err(),
+            CheckpointError::UnsupportedVersion(0)
+        );
+    }
+
+    #[test]
+    fn firing_order() {
+        let solve = |general_firing_order| {
+            let attackers = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+            let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+            let sequence = BattlePhase::create_sequence(&attackers, &defenders);
+            let roll_selector = RollSelector {
+                general_firing_order,
+                ..Default::default()
+            };
+            let combat_manager = get_combat_manager_with::<ExactProbability>(roll_selector);
+            MarkovSolver::new(combat_manager, sequence, attackers, defenders)
+                .solve()
+                .summarize()
+        };
+
+        // Each round, the attacker hits with 1/6 and the defender with 2/6.
+        let summary = solve(FiringOrder::Simultaneous);
+        assert_eq!(summary.attacker.win_p, ExactProbability::from_ratio(1, 4));
+        assert_eq!(summary.draw_p, ExactProbability::from_ratio(1, 8));
+
+        // A defender hit by the attacker never fires back.
+        let summary = solve(FiringOrder::AttackerFirst);
+        assert_eq!(summary.attacker.win_p, ExactProbability::from_ratio(3, 8));
+        assert_eq!(summary.defender.win_p, ExactProbability::from_ratio(5, 8));
+        assert_eq!(summary.draw_p, ExactProbability::zero());
+
+        // An attacker hit by the defender never fires back.
+        let summary = solve(FiringOrder::DefenderFirst);
+        assert_eq!(summary.attacker.win_p, ExactProbability::from_ratio(1, 4));
+        assert_eq!(summary.defender.win_p, ExactProbability::from_ratio(3, 4));
+        assert_eq!(summary.draw_p, ExactProbability::zero());
+    }
+
+    #[test]
+    fn firer_chooses_casualties() {
+        let defenders_lost = |firer_chooses_casualties| {
+            let combat = Combat {
+                battle_phase: BattlePhase::General,
+                attackers: Force::new(vec![Quant::new(Unit::Tank, 1)].into()),
+                defenders: Force::new(
+                    vec![Quant::new(Unit::Infantry, 1), Quant::new(Unit::Fighter, 1)].into(),
+                ),
+            };
+            let roll_selector = RollSelector {
+                firer_chooses_casualties,
+                ..Default::default()
+            };
+            let combat_manager = get_combat_manager_with::<ExactProbability>(roll_selector);
+            let result = combat_manager.resolve(&Prob::new(combat, ExactProbability::one()));
+            let mut lost = Vec::new();
+            for survivors in result.surviving_defenders.outcomes() {
+                let unit = if survivors.item.count(&Unit::Infantry) == 0 {
+                    Some(Unit::Infantry)
+                } else if survivors.item.count(&Unit::Fighter) == 0 {
+                    Some(Unit::Fighter)
+                } else {
+                    None
+                };
+                lost.push((unit, survivors.p.clone()));
+            }
+            lost.sort_by_key(|(unit, _)| *unit);
+            lost
+        };
+
+        // The defender takes the cheapest casualty.
+        let half = || ExactProbability::from_ratio(1, 2);
+        assert_eq!(
+            defenders_lost(false),
+            vec![(None, half()), (Some(Unit::Infantry), half())]
+        );
+
+        // The attacker targets the most expensive unit instead.
+        assert_eq!(
+            defenders_lost(true),
+            vec![(None, half()), (Some(Unit::Fighter), half())]
+        );
+    }
+
+    #[test]
+    fn targets_damaged_battleships_first() {
+        let selector = SurvivorSelector {
+            removal_order: SurvivorSelector::most_expensive_order(),
+            reserved: Vec::new(),
+        };
+        let force: QuantDist<Unit> = vec![
+            Quant::new(Unit::Battleship, 1),
+            Quant::new(Unit::BattleshipDamaged, 1),
+        ]
+        .into();
+        let hits: QuantDist<Hit> = vec![Quant::new(Hit::Targeted, 1)].into();
+
+        // Hitting the healthy battleship would only damage it.
+        let survivors = selector.select_survivors(&force, &hits);
+        assert_eq!(survivors.count(&Unit::Battleship), 1);
+        assert_eq!(survivors.count(&Unit::BattleshipDamaged), 0);
+    }
+
+    #[test]
+    fn optimal_casualties() {
+        let survivors_of_one_hit = |objective| {
+            let combat = Combat {
+                battle_phase: BattlePhase::General,
+                attackers: Force::new(
+                    vec![Quant::new(Unit::Infantry, 1), Quant::new(Unit::Fighter, 1)].into(),
+                ),
+                defenders: Force::new(vec![Quant::new(Unit::Infantry, 1)].into()),
+            };
+            let combat_manager = get_optimal_combat_manager_with::<Probability>(
+                RollSelector::default(),
+                objective,
+                1,
+            );
+            let result = combat_manager.resolve(&Prob::new(combat, Probability::one()));
+            result
+                .surviving_attackers
+                .outcomes()
+                .iter()
+                .map(|survivors| survivors.item.clone())
+                .find(|survivors| survivors.outcomes().iter().map(|q| q.count).sum::<u32>() == 1)
+                .unwrap()
+        };
+
+        // Only the infantry can take the territory.
+        let survivors = survivors_of_one_hit(Objective::CaptureProbability);
+        assert_eq!(survivors.count(&Unit::Infantry), 1);
+
+        // But the fighter is more likely to win.
+        let survivors = survivors_of_one_hit(Objective::WinProbability);
+        assert_eq!(survivors.count(&Unit::Fighter), 1);
+    }
+
+    #[test]
+    fn optimal_targets() {
+        let combat = Combat {
+            battle_phase: BattlePhase::General,
+            attackers: Force::new(vec![Quant::new(Unit::Tank, 1)].into()),
+            defenders: Force::new(
+                vec![Quant::new(Unit::Bomber, 1), Quant::new(Unit::Fighter, 1)].into(),
+            ),
+        };
+        let roll_selector = RollSelector {
+            firer_chooses_casualties: true,
+            ..Default::default()
+        };
+        let combat_manager = get_optimal_combat_manager_with::<Probability>(
+            roll_selector,
+            Objective::WinProbability,
+            1,
+        );
+        let result = combat_manager.resolve(&Prob::new(combat, Probability::one()));
+        let survivors = result
+            .surviving_defenders
+            .outcomes()
+            .iter()
+            .map(|survivors| survivors.item.clone())
+            .find(|survivors| survivors.outcomes().iter().map(|q| q.count).sum::<u32>() == 1)
+            .unwrap();
+
+        // The bomber is the most expensive target, but the fighter defends far better.
+        assert_eq!(survivors.count(&Unit::Bomber), 1);
+    }
+
+    #[test]
+    fn game_rounds() {
+        let sequence = PhaseSequence::new(
+            vec![BattlePhase::Bombardment, BattlePhase::AntiAir],
+            vec![BattlePhase::SurpriseStrike, BattlePhase::General],
+        );
+        let game_rounds: Vec<_> = (0..8).map(|i| sequence.game_round_at(i)).collect();
+        assert_eq!(game_rounds, vec![0, 1, 1, 1, 1, 2, 2, 3]);
+
+        let attackers = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 1),
+                Quant::new(Unit::BombardingCruiser, 1),
+            ]
+            .into(),
+        );
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let sequence = BattlePhase::create_sequence(&attackers, &defenders);
+
+        let (mut summarizer, mut round_manager) = setup(attackers, defenders);
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // The bombardment and the first general round both make up the first game round.
+        let game_round_summaries = summary.game_round_summaries(&sequence);
+        assert_eq!(game_round_summaries.len(), summary.round_count() - 1);
+        let first = &game_round_summaries[0];
+        let bombardment = &summary.round_summaries[0];
+        let general = &summary.round_summaries[1];
+        assert_eq!(first.index, 1);
+        assert!(assert_prob_eq(
+            first.attacker.win_p,
+            bombardment.attacker.win_p + general.attacker.win_p,
+            2
+        ));
+        assert_eq!(first.defender.unit_count, general.defender.unit_count);
+
+        // After the bombardment, every general round ends the battle with 16/36, so the battle
+        // lasts another 36/16 rounds on average if it isn't over.
+        let length_dist = summary.length_dist(&sequence);
+        assert_eq!(length_dist.outcomes()[0].item, 1);
+        assert!(assert_prob_eq(length_dist.total_p(), Probability::one(), 4));
+        let bombarded = 0.5;
+        let expected = bombarded * 1.0 + (1.0 - bombarded) * 36.0 / 16.0;
+        assert!(approx_eq!(
+            f64,
+            summary.expected_length(&sequence),
+            expected,
+            epsilon = 1e-9
+        ));
+    }
+
+    #[test]
+    fn parallel_matches_serial() {
+        let attackers = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 4),
+                Quant::new(Unit::Artillery, 2),
+                Quant::new(Unit::Tank, 2),
+                Quant::new(Unit::Fighter, 2),
+                Quant::new(Unit::BombardingCruiser, 1),
+            ]
+            .into(),
+        );
+        let defenders = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 6),
+                Quant::new(Unit::Tank, 1),
+                Quant::new(Unit::AntiAir, 1),
+            ]
+            .into(),
+        );
+
+        let (mut serial_summarizer, mut serial_manager) =
+            setup(attackers.clone(), defenders.clone());
+        let (mut parallel_summarizer, mut parallel_manager) = setup(attackers, defenders);
+        while !serial_manager.is_complete() {
+            serial_summarizer.add_round(serial_manager.advance_round());
+            parallel_summarizer.add_round(parallel_manager.advance_round_parallel());
+
+            let serial_round = serial_manager.last_round();
+            let parallel_round = parallel_manager.last_round();
+            assert_eq!(serial_round.pending.len(), parallel_round.pending.len());
+            assert_eq!(serial_round.completed.len(), parallel_round.completed.len());
+        }
+        assert!(parallel_manager.is_complete());
+
+        let serial = serial_summarizer.summarize();
+        let parallel = parallel_summarizer.summarize();
+        assert!(assert_prob_eq(
+            serial.attacker.win_p,
+            parallel.attacker.win_p,
+            8
+        ));
+        assert!(assert_prob_eq(
+            serial.defender.win_p,
+            parallel.defender.win_p,
+            8
+        ));
+        assert!(assert_prob_eq(serial.draw_p, parallel.draw_p, 8));
+        assert!(approx_eq!(
+            f64,
+            serial.attacker.ipc.mean,
+            parallel.attacker.ipc.mean,
+            epsilon = 0.000000001
+        ));
+        assert_eq!(
+            serial.completed_combats.len(),
+            parallel.completed_combats.len()
+        );
+    }
+
+    #[test]
+    fn exact_roll_hits() {
+        use calc::Roll;
+
+        let strike: QuantDist<Roll<Unit, Hit>> =
+            vec![Quant::new(Roll::new(1, Hit::NotSubmarines), 2)].into();
+        let hits: ProbDist<QuantDist<Hit>, ExactProbability> = roll_hits(&strike);
+
+        assert_eq!(hits.len(), 3);
+        for prob in hits.outcomes() {
+            let expected = match prob.item.count(&Hit::NotSubmarines) {
+                0 => ExactProbability::from_ratio(25, 36),
+                1 => ExactProbability::from_ratio(10, 36),
+                2 => ExactProbability::from_ratio(1, 36),
+                _ => unreachable!(),
+            };
+            assert_eq!(prob.p, expected);
+        }
+    }
+
+    #[test]
+    fn roll_hits_d12() {
+        use calc::Roll;
+
+        let strike: QuantDist<Roll<Unit, Hit>> = vec![
+            Quant::new(Roll::with_sides(3, 12, Hit::AllUnits), 1),
+            Quant::new(Roll::with_sides(1, 12, Hit::AllUnits), 1),
+        ]
+        .into();
+        let hits: ProbDist<QuantDist<Hit>, ExactProbability> = roll_hits(&strike);
+
+        assert_eq!(hits.len(), 3);
+        for prob in hits.outcomes() {
+            let expected = match prob.item.count(&Hit::AllUnits) {
+                0 => ExactProbability::from_ratio(99, 144),
+                1 => ExactProbability::from_ratio(42, 144),
+                2 => ExactProbability::from_ratio(3, 144),
+                _ => unreachable!(),
+            };
+            assert_eq!(prob.p, expected);
+        }
+
+        // The same strength on a different die must not be served from the cache.
+        let roller: Roller<Unit, Hit> = Default::default();
+        let d6 = roller.roll_hits(
+            vec![Quant::new(Roll::new(3, Hit::AllUnits), 1)].into(),
+            DiceMode::Standard,
+        );
+        let d12 = roller.roll_hits(
+            vec![Quant::new(Roll::with_sides(3, 12, Hit::AllUnits), 1)].into(),
+            DiceMode::Standard,
+        );
+        assert_ne!(d6, d12);
+    }
+
+    #[test]
+    fn roll_hits_automatic() {
+        use calc::Roll;
+
+        for strength in 6..=8 {
+            let strike: QuantDist<Roll<Unit, Hit>> =
+                vec![Quant::new(Roll::new(strength, Hit::AllUnits), 3)].into();
+            let hits: ProbDist<QuantDist<Hit>> = roll_hits(&strike);
+            let exact: ProbDist<QuantDist<Hit>, ExactProbability> = roll_hits(&strike);
+
+            assert_eq!(hits.len(), 1);
+            assert_eq!(hits.outcomes()[0].item.count(&Hit::AllUnits), 3);
+            assert_eq!(hits.outcomes()[0].p, Probability::one());
+            assert_eq!(exact.outcomes()[0].p, ExactProbability::one());
+        }
+    }
+
+    #[test]
+    fn roll_hits_low_luck() {
+        use calc::Roll;
+
+        let strike: QuantDist<Roll<Unit, Hit>> = vec![
+            Quant::new(Roll::new(3, Hit::AllUnits), 2),
+            Quant::new(Roll::new(1, Hit::AllUnits), 2),
+            Quant::new(Roll::new(4, Hit::NotSubmarines), 1),
+        ]
+        .into();
+        let hits: ProbDist<QuantDist<Hit>, ExactProbability> = calc::roll_hits_low_luck(&strike);
+
+        // 8 strength for all units is 1 guaranteed hit and a 2 in 6 chance of another, while the
+        // 4 strength roll is a 4 in 6 chance of a hit.
+        assert_eq!(hits.len(), 4);
+        for prob in hits.outcomes() {
+            let all_units = prob.item.count(&Hit::AllUnits);
+            let not_subs = prob.item.count(&Hit::NotSubmarines);
+            let expected = match (all_units, not_subs) {
+                (1, 0) => ExactProbability::from_ratio(8, 36),
+                (1, 1) => ExactProbability::from_ratio(16, 36),
+                (2, 0) => ExactProbability::from_ratio(4, 36),
+                (2, 1) => ExactProbability::from_ratio(8, 36),
+                _ => unreachable!(),
+            };
+            assert_eq!(prob.p, expected);
+        }
+    }
+
+    #[test]
+    fn low_luck() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 2)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) =
+            setup_with(attackers, defenders, RollSelector::low_luck(false));
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // The tanks are guaranteed to kill the infantry in the first round; the infantry only
+        // takes a tank with it on a roll of 1 or 2.
+        assert_eq!(summary.round_count(), 1);
+        assert_eq!(summary.attacker.win_p, Probability::one());
+        assert_eq!(summary.draw_p, Probability::zero());
+        assert_eq!(summary.defender.ipc_lost.mean, 3.0);
+        assert!(approx_eq!(
+            f64,
+            summary.attacker.ipc_lost.mean,
+            2.0,
+            ulps = 2
+        ));
+    }
+
+    #[test]
+    fn low_luck_anti_air() {
+        let attackers = Force::new(vec![Quant::new(Unit::Fighter, 3)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::AntiAir, 1)].into());
+
+        // Without low luck anti-air fire, each fighter is shot down on its own roll.
+        let (_, mut round_manager) = setup_with(
+            attackers.clone(),
+            defenders.clone(),
+            RollSelector::low_luck(false),
+        );
+        let round = round_manager.advance_round();
+        assert_eq!(round.surviving_attackers.len(), 4);
+
+        // With it, the three rolls at strength 1 are a 3 in 6 chance of a single hit.
+        let (_, mut round_manager) = setup_with(attackers, defenders, RollSelector::low_luck(true));
+        let round = round_manager.advance_round();
+        assert_eq!(round.surviving_attackers.len(), 2);
+        for survivors in round.surviving_attackers.outcomes() {
+            assert!(assert_prob_eq(
+                survivors.p,
+                Probability::from_ratio(1, 2),
+                1
+            ));
+        }
+    }
+
+    #[test]
+    fn exact_bombardment() {
+        let attackers = Force::new(vec![Quant::new(Unit::BombardingBattleship, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let (mut summarizer, mut round_manager) =
+            setup_exact(attackers, defenders, ExactProbability::zero());
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        assert_eq!(summary.attacker.win_p, ExactProbability::zero());
+        assert_eq!(summary.defender.win_p, ExactProbability::from_ratio(1, 3));
+        assert_eq!(summary.draw_p, ExactProbability::from_ratio(2, 3));
+        assert_eq!(summary.total_p, ExactProbability::one());
+        assert_eq!(summary.pruned_p, ExactProbability::zero());
+    }
+
+    #[test]
+    fn exact_conservation() {
+        let attackers =
+            Force::new(vec![Quant::new(Unit::Tank, 1), Quant::new(Unit::Bomber, 1)].into());
+        let defenders =
+            Force::new(vec![Quant::new(Unit::Tank, 1), Quant::new(Unit::Fighter, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup_exact(
+            attackers,
+            defenders,
+            ExactProbability::from_ratio(1, 1_000_000),
+        );
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let summary = summarizer.summarize();
+
+        // Without rounding error, every bit of probability is either completed or pruned.
+        assert_eq!(
+            summary.attacker.win_p + summary.defender.win_p + summary.draw_p + summary.pruned_p,
+            ExactProbability::one()
+        );
+        assert!(summary.total_p < ExactProbability::one());
+    }
+
+    #[test]
+    fn markov_exact() {
+        let attackers = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        let solution = create_markov_solver::<ExactProbability>(attackers, defenders).solve();
+        let summary = solution.summarize();
+
+        // Each round, the attacker wins 4/36, the defender wins 10/36, both die 2/36, and
+        // otherwise the round repeats.
+        assert_eq!(summary.attacker.win_p, ExactProbability::from_ratio(1, 4));
+        assert_eq!(summary.defender.win_p, ExactProbability::from_ratio(5, 8));
+        assert_eq!(summary.draw_p, ExactProbability::from_ratio(1, 8));
+        assert_eq!(summary.total_p, ExactProbability::one());
+        assert_eq!(solution.stalemate_p(), ExactProbability::zero());
+    }
+
+    #[test]
+    fn markov_stalemate() {
+        let attackers = Force::new(
+            vec![
+                Quant::new(Unit::Submarine, 1),
+                Quant::new(Unit::Destroyer, 1),
+            ]
+            .into(),
+        );
+        let defenders = Force::new(vec![Quant::new(Unit::Fighter, 1)].into());
+
+        let solution = create_markov_solver::<ExactProbability>(attackers, defenders).solve();
+
+        // The fighter can't hit the submarine, and the submarine can't hit the fighter, so the
+        // battle only completes if the fighter is hit by the destroyer before it hits the
+        // destroyer.
+        assert_eq!(solution.stalemated.len(), 1);
+        let stalemate = &solution.stalemated.outcomes()[0].item;
+        assert_eq!(stalemate.attackers.count(&Unit::Destroyer), 0);
+        assert_eq!(stalemate.attackers.count(&Unit::Submarine), 1);
+
+        let summary = solution.summarize();
+        assert_eq!(
+            summary.attacker.win_p + summary.draw_p + solution.stalemate_p(),
+            ExactProbability::one()
+        );
+        assert_eq!(summary.defender.win_p, ExactProbability::zero());
+        assert!(solution.stalemate_p() > ExactProbability::zero());
+    }
+
+    #[test]
+    fn markov_matches_rounds() {
+        let attackers = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 3),
+                Quant::new(Unit::Artillery, 1),
+                Quant::new(Unit::Fighter, 1),
+                Quant::new(Unit::BombardingCruiser, 1),
+            ]
+            .into(),
+        );
+        let defenders = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 4),
+                Quant::new(Unit::Tank, 1),
+                Quant::new(Unit::AntiAir, 1),
+            ]
+            .into(),
+        );
+
+        let (mut summarizer, mut round_manager) = setup(attackers.clone(), defenders.clone());
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let rounds = summarizer.summarize();
+        let solution = create_markov_solver::<Probability>(attackers, defenders).solve();
+        let markov = solution.summarize();
+
+        assert!(solution.stalemated.is_empty());
+        assert!(approx_eq!(
+            f64,
+            markov.attacker.win_p.into(),
+            rounds.attacker.win_p.into(),
+            epsilon = 1e-12
+        ));
+        assert!(approx_eq!(
+            f64,
+            markov.defender.win_p.into(),
+            rounds.defender.win_p.into(),
+            epsilon = 1e-12
+        ));
+        assert!(approx_eq!(
+            f64,
+            markov.draw_p.into(),
+            rounds.draw_p.into(),
+            epsilon = 1e-12
+        ));
+        assert!(approx_eq!(
+            f64,
+            markov.attacker.ipc_lost.mean,
+            rounds.attacker.ipc_lost.mean,
+            epsilon = 1e-9
+        ));
+    }
+
+    #[test]
+    fn simulation_matches_exact() {
+        let attackers = Force::new(
+            vec![
+                Quant::new(Unit::Infantry, 3),
+                Quant::new(Unit::Artillery, 1),
+                Quant::new(Unit::Tank, 1),
+            ]
+            .into(),
+        );
+        let defenders =
+            Force::new(vec![Quant::new(Unit::Infantry, 4), Quant::new(Unit::Fighter, 1)].into());
+
+        let (mut summarizer, mut round_manager) = setup(attackers.clone(), defenders.clone());
+        run_to_completion(&mut round_manager, &mut summarizer);
+        let exact = summarizer.summarize();
+
+        let simulation: SimulationSummary<_, _> =
+            create_simulator(attackers, defenders, 20_000, 42).run();
+        let estimate = &simulation.summary;
+        assert_eq!(simulation.trials, 20_000);
+        assert!(assert_prob_eq(estimate.total_p, Probability::one(), 1_000));
+
+        let confidence = 0.999;
+        assert!(simulation
+            .attacker_win_interval(confidence)
+            .contains(exact.attacker.win_p.into()));
+        assert!(simulation
+            .defender_win_interval(confidence)
+            .contains(exact.defender.win_p.into()));
+        assert!(simulation
+            .draw_interval(confidence)
+            .contains(exact.draw_p.into()));
+        assert!(simulation
+            .mean_interval(&estimate.attacker.ipc_lost, confidence)
+            .contains(exact.attacker.ipc_lost.mean));
+        assert!(simulation
+            .mean_interval(&estimate.defender.ipc_lost, confidence)
+            .contains(exact.defender.ipc_lost.mean));
+        assert!(simulation.attacker_win_interval(confidence).width() < 0.05);
+    }
+
+    #[test]
+    fn simulation_is_seeded() {
+        let attackers = Force::new(vec![Quant::new(Unit::Tank, 2)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 3)].into());
+
+        let run = |seed| -> SimulationSummary<_, _> {
+            create_simulator(attackers.clone(), defenders.clone(), 1_000, seed).run()
+        };
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn mixed_hit_rolls() {
+        // A bomber and a submarine roll different hits, and every outcome of one must be
+        // combined with every outcome of the other.
+        let strike: QuantDist<Roll<Unit, Hit>> = vec![
+            Quant::new(Roll::new(4, Hit::NotSubmarines), 1),
+            Quant::new(Roll::new(2, Hit::NotAirUnits), 1),
+        ]
+        .into();
+        let hits: ProbDist<QuantDist<Hit>> = roll_hits(&strike);
+        assert_eq!(hits.len(), 4);
+
+        // Both hit with 4/6 * 2/6.
+        let both = hits
+            .outcomes()
+            .iter()
+            .find(|outcome| {
+                outcome.item.count(&Hit::NotSubmarines) == 1
+                    && outcome.item.count(&Hit::NotAirUnits) == 1
+            })
+            .unwrap();
+        assert!(assert_prob_eq(both.p, Probability::from_ratio(8, 36), 1));
+    }
+
+    #[test]
+    fn simulation_round_limit() {
+        let attackers = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+        let defenders = Force::new(vec![Quant::new(Unit::Infantry, 1)].into());
+
+        // Every trial still fighting after the first round is cut off, and counted as pruned.
+        let mut simulator = create_simulator::<Probability>(attackers, defenders, 1_000, 1);
+        simulator.set_round_limit(1);
+        let simulation = simulator.run();
+        let summary = &simulation.summary;
+        assert!(summary.pruned_p > Probability::zero());
+        assert!(assert_prob_eq(
+            summary.total_p + summary.pruned_p,
+            Probability::one(),
+            1_000
+        ));
+    }
+
+    type TestRoundManager<TProb = Probability> =
+        RoundManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector, TProb>;
+
+    fn setup(
+        attackers: Force<Unit>,
+        defenders: Force<Unit>,
+    ) -> (Summarizer<BattlePhase, Unit>, TestRoundManager) {
+        setup_with(attackers, defenders, RollSelector::default())
+    }
+
+    fn setup_with(
+        attackers: Force<Unit>,
+        defenders: Force<Unit>,
+        roll_selector: RollSelector,
+    ) -> (Summarizer<BattlePhase, Unit>, TestRoundManager) {
+        let sequence = BattlePhase::create_sequence(&attackers, &defenders);
+        let combat_manager = get_combat_manager_with(roll_selector);
+
+        let mut round_manager =
+            RoundManager::new(combat_manager, sequence.clone(), attackers, defenders);
+        round_manager.set_prune_threshold(Probability::zero());
+        let summary = Summarizer::new(round_manager.last_round());
+        (summary, round_manager)
+    }
+
+    fn setup_exact(
+        attackers: Force<Unit>,
+        defenders: Force<Unit>,
+        prune_threshold: ExactProbability,
+    ) -> (
+        Summarizer<BattlePhase, Unit, ExactProbability>,
+        TestRoundManager<ExactProbability>,
+    ) {
+        let mut round_manager = create_round_manager(attackers, defenders);
+        round_manager.set_prune_threshold(prune_threshold);
         let summary = Summarizer::new(round_manager.last_round());
         (summary, round_manager)
     }
 
-    fn run_to_completion<'a>(
-        round_manager: &'a mut RoundManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector>,
-        summary: &mut Summarizer<BattlePhase, Unit>,
-    ) -> &'a RoundResult<BattlePhase, Unit> {
+    fn run_to_completion<'a, TProb: ProbValue>(
+        round_manager: &'a mut TestRoundManager<TProb>,
+        summary: &mut Summarizer<BattlePhase, Unit, TProb>,
+    ) -> &'a RoundResult<BattlePhase, Unit, TProb> {
         while !round_manager.is_complete() {
-            summary.add_round(&round_manager.advance_round());
+            summary.add_round(round_manager.advance_round());
         }
         round_manager.advance_round()
     }
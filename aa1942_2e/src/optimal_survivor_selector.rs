@@ -0,0 +1,314 @@
+use crate::*;
+use calc::{
+    Combat, CombatContext, CombatManager, Force, PhaseSequence, ProbDist, ProbValue, Probability,
+    QuantDistBuilder, RoundManager, Side,
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex},
+};
+
+/// The goal a side pursues when choosing its casualties.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Objective {
+    /// Maximize the probability of winning the battle.
+    WinProbability,
+    /// Maximize the expected TUV swing in favour of the side.
+    TuvSwing,
+    /// Maximize the probability of the territory being captured when attacking, or minimize it
+    /// when defending.
+    CaptureProbability,
+}
+
+type DecisionKey = (Force<Unit>, Force<Unit>, Side, QuantDist<Hit>);
+
+type LookaheadManager = CombatManager<BattlePhase, Unit, Hit, RollSelector, SurvivorSelector>;
+
+/// Selects the casualties which best serve an `Objective`, by fighting out the battle for a
+/// bounded number of rounds after each legal choice of casualties.
+///
+/// The lookahead is fought with the standard removal orders, pruning any outcomes at or below
+/// the prune threshold, and each candidate is evaluated against the hostile force as it was
+/// before the current round, since the hostile casualties of the same round aren't known yet.
+/// When no choice is strictly better, the casualties of the fallback selector are taken.
+/// Decisions are cached per pair of forces and hit combination, and the combat managers of the
+/// lookahead are kept between decisions so that their rolls stay cached.
+pub struct OptimalSurvivorSelector {
+    objective: Objective,
+    lookahead: usize,
+    targeting: bool,
+    roll_selector: RollSelector,
+    fallback: SurvivorSelector,
+    prune_threshold: Probability,
+    lookahead_managers: Mutex<Vec<LookaheadManager>>,
+    decisions: Mutex<HashMap<DecisionKey, Force<Unit>>>,
+}
+
+impl OptimalSurvivorSelector {
+    /// The default pruning threshold of the lookahead.
+    pub const DEFAULT_PRUNE_THRESHOLD: f64 = 1e-6;
+
+    /// Constructs a new `OptimalSurvivorSelector` choosing the casualties of its own side in
+    /// pursuit of `objective`, looking ahead the given number of game rounds with the rolls of
+    /// `roll_selector`. `fallback` is used whenever the rest of the combat isn't known, and to
+    /// break ties.
+    ///
+    /// Panics if `lookahead` is zero.
+    pub fn new(
+        objective: Objective,
+        lookahead: usize,
+        roll_selector: RollSelector,
+        fallback: SurvivorSelector,
+    ) -> Self {
+        Self::with_targeting(objective, lookahead, false, roll_selector, fallback)
+    }
+
+    /// Constructs a new `OptimalSurvivorSelector` for use as a target selector, choosing the
+    /// casualties of the hostile side in pursuit of `objective` for the firing side.
+    ///
+    /// Panics if `lookahead` is zero.
+    pub fn targeting(
+        objective: Objective,
+        lookahead: usize,
+        roll_selector: RollSelector,
+        fallback: SurvivorSelector,
+    ) -> Self {
+        Self::with_targeting(objective, lookahead, true, roll_selector, fallback)
+    }
+
+    fn with_targeting(
+        objective: Objective,
+        lookahead: usize,
+        targeting: bool,
+        roll_selector: RollSelector,
+        fallback: SurvivorSelector,
+    ) -> Self {
+        if lookahead == 0 {
+            panic!("The lookahead must be at least one round.");
+        }
+
+        OptimalSurvivorSelector {
+            objective,
+            lookahead,
+            targeting,
+            roll_selector,
+            fallback,
+            prune_threshold: Probability::new(Self::DEFAULT_PRUNE_THRESHOLD),
+            lookahead_managers: Mutex::new(vec![get_combat_manager_with(roll_selector)]),
+            decisions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the objective this selector pursues.
+    pub fn objective(&self) -> Objective {
+        self.objective
+    }
+
+    /// Returns the number of game rounds this selector looks ahead.
+    pub fn lookahead(&self) -> usize {
+        self.lookahead
+    }
+
+    /// Indicates whether or not this selector chooses the casualties of the hostile side.
+    pub fn is_targeting(&self) -> bool {
+        self.targeting
+    }
+
+    /// Sets the pruning threshold of the lookahead, where outcomes with a probability equal to
+    /// or below are pruned.
+    pub fn set_prune_threshold(&mut self, p: Probability) {
+        self.prune_threshold = p;
+    }
+
+    /// Returns the number of cached decisions.
+    pub fn cached_decisions(&self) -> usize {
+        self.decisions.lock().unwrap().len()
+    }
+
+    fn choose(
+        &self,
+        combat: &Combat<BattlePhase, Unit>,
+        side: Side,
+        hits: &QuantDist<Hit>,
+    ) -> Force<Unit> {
+        let key = (
+            combat.attackers.clone(),
+            combat.defenders.clone(),
+            side,
+            hits.clone(),
+        );
+        if let Some(survivors) = self.decisions.lock().unwrap().get(&key) {
+            return survivors.clone();
+        }
+
+        let friendlies = SurvivorSelector::without_nontargetable(match side {
+            Side::Attacker => &combat.attackers,
+            Side::Defender => &combat.defenders,
+        });
+        let mut best = Arc::new(self.fallback.select_survivors(&friendlies, hits));
+        let mut best_score = self.score(combat, side, &best);
+        for candidate in Self::candidates(&friendlies, hits) {
+            let candidate = Arc::new(candidate);
+            let score = self.score(combat, side, &candidate);
+            if score > best_score {
+                best = candidate;
+                best_score = score;
+            }
+        }
+
+        self.decisions.lock().unwrap().insert(key, best.clone());
+        best
+    }
+
+    /// Returns every distinct force which could survive `hits`, taking the most restrictive
+    /// hits first.
+    fn candidates(force: &QuantDist<Unit>, hits: &QuantDist<Hit>) -> BTreeSet<QuantDist<Unit>> {
+        let mut candidates = BTreeSet::new();
+        candidates.insert(force.clone());
+        for hit in &Hit::order() {
+            for _ in 0..hits.count(hit) {
+                candidates = candidates
+                    .iter()
+                    .flat_map(|candidate| Self::take_hit(candidate, *hit))
+                    .collect();
+            }
+        }
+        candidates
+    }
+
+    /// Returns every force resulting from `force` taking a single `hit`.
+    fn take_hit(force: &QuantDist<Unit>, hit: Hit) -> Vec<QuantDist<Unit>> {
+        use calc::Hit;
+
+        let targets: Vec<Unit> = force
+            .outcomes()
+            .iter()
+            .filter(|quant| hit.hits(quant.item))
+            .map(|quant| quant.item)
+            .collect();
+        if targets.is_empty() {
+            return vec![force.clone()];
+        }
+
+        targets
+            .into_iter()
+            .map(|unit| {
+                let mut survivors: QuantDistBuilder<Unit> = force.clone().into();
+                survivors.remove(&unit, 1);
+                if let Some(replacement) = unit.damaged() {
+                    survivors.add(replacement, 1);
+                }
+                survivors.build()
+            })
+            .collect()
+    }
+
+    /// Returns the side pursuing the objective when the casualties of `side` are chosen.
+    fn perspective(&self, side: Side) -> Side {
+        match (self.targeting, side) {
+            (false, side) => side,
+            (true, Side::Attacker) => Side::Defender,
+            (true, Side::Defender) => Side::Attacker,
+        }
+    }
+
+    /// Evaluates `survivors` of `side` against the objective of the side pursuing it, higher
+    /// being better.
+    fn score(
+        &self,
+        combat: &Combat<BattlePhase, Unit>,
+        side: Side,
+        survivors: &Force<Unit>,
+    ) -> f64 {
+        let perspective = self.perspective(side);
+        let context = CombatContext::from_combat(combat, side);
+        let ipc_lost = force_ipc(context.friendlies()) as f64 - force_ipc(survivors) as f64;
+        let ipc_gained = if perspective == side {
+            -ipc_lost
+        } else {
+            ipc_lost
+        };
+        let combat = combat.with_force(side, survivors.clone());
+        if combat.completed() {
+            return match self.objective {
+                Objective::WinProbability => (combat.winner() == Some(perspective)) as u32 as f64,
+                Objective::TuvSwing => ipc_gained,
+                Objective::CaptureProbability => match perspective {
+                    Side::Attacker => combat.captured() as u32 as f64,
+                    Side::Defender => -(combat.captured() as u32 as f64),
+                },
+            };
+        }
+
+        // The phases at the start of the battle only occur once, so the lookahead begins with
+        // the next cycle.
+        let sequence = BattlePhase::create_sequence(&combat.attackers, &combat.defenders);
+        let sequence = PhaseSequence::new(Vec::new(), sequence.cycle().to_vec());
+        let combat_manager = self.lookahead_managers.lock().unwrap().pop();
+        let combat_manager =
+            combat_manager.unwrap_or_else(|| get_combat_manager_with(self.roll_selector));
+        let round_limit = self.lookahead * sequence.cycle().len();
+        let mut round_manager =
+            RoundManager::new(combat_manager, sequence, combat.attackers, combat.defenders);
+        round_manager.set_prune_threshold(self.prune_threshold);
+        round_manager.set_round_limit(round_limit);
+        let mut summarizer = Summarizer::new(round_manager.last_round());
+        while !round_manager.is_complete() {
+            summarizer.add_round(round_manager.advance_round());
+        }
+        let summary = summarizer.summarize();
+        self.lookahead_managers
+            .lock()
+            .unwrap()
+            .push(round_manager.into_combat_manager());
+
+        match (self.objective, perspective) {
+            (Objective::WinProbability, Side::Attacker) => summary.attacker.win_p.to_f64(),
+            (Objective::WinProbability, Side::Defender) => summary.defender.win_p.to_f64(),
+            (Objective::TuvSwing, Side::Attacker) => summary.tuv_swing.swing.mean + ipc_gained,
+            (Objective::TuvSwing, Side::Defender) => -summary.tuv_swing.swing.mean + ipc_gained,
+            (Objective::CaptureProbability, Side::Attacker) => summary.capture_p.to_f64(),
+            (Objective::CaptureProbability, Side::Defender) => -summary.capture_p.to_f64(),
+        }
+    }
+}
+
+fn force_ipc(force: &QuantDist<Unit>) -> u32 {
+    use calc::Unit;
+
+    force
+        .outcomes()
+        .iter()
+        .map(|quant| quant.item.ipc() * quant.count)
+        .sum()
+}
+
+impl<TProb: ProbValue> calc::SurvivorSelector<Unit, Hit, TProb> for OptimalSurvivorSelector {
+    fn select(
+        &self,
+        starting_force: &QuantDist<Unit>,
+        hit_dists: &ProbDist<QuantDist<Hit>, TProb>,
+    ) -> ProbDist<Force<Unit>, TProb> {
+        calc::SurvivorSelector::select(&self.fallback, starting_force, hit_dists)
+    }
+
+    fn select_in<TBattlePhase: calc::BattlePhase>(
+        &self,
+        context: &CombatContext<TBattlePhase, Unit>,
+        hit_dists: &ProbDist<QuantDist<Hit>, TProb>,
+    ) -> ProbDist<Force<Unit>, TProb> {
+        // Only the forces matter to the lookahead, which begins with the next cycle.
+        let combat = Combat {
+            battle_phase: BattlePhase::General,
+            attackers: context.combat.attackers.clone(),
+            defenders: context.combat.defenders.clone(),
+        };
+        let mut result = ProbDistBuilder::<Force<Unit>, TProb>::new();
+        for hit_dist in hit_dists.outcomes() {
+            let survivors = self.choose(&combat, context.side, &hit_dist.item);
+            result.add(survivors, hit_dist.p.clone());
+        }
+        result.build()
+    }
+}
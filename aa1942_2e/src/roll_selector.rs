@@ -1,7 +1,35 @@
 use crate::*;
-use calc::{QuantDistBuilder, Roll};
+use calc::{DiceMode, FiringOrder, QuantDistBuilder, Roll};
 
-pub struct RollSelector;
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RollSelector {
+    /// The dice mode used for every phase other than anti-air fire.
+    pub dice_mode: DiceMode,
+    /// The dice mode used for anti-air fire.
+    pub anti_air_dice_mode: DiceMode,
+    /// The firing order of general combat, which is simultaneous under the standard rules. Every
+    /// other phase is always simultaneous.
+    pub general_firing_order: FiringOrder,
+    /// House rule where the firing side chooses the casualties of its general combat hits
+    /// which can't hit submarines, rather than the side receiving them.
+    pub firer_chooses_casualties: bool,
+}
+
+impl RollSelector {
+    /// Constructs a `RollSelector` using Low Luck rules, optionally including anti-air fire.
+    pub fn low_luck(include_anti_air: bool) -> Self {
+        RollSelector {
+            dice_mode: DiceMode::LowLuck,
+            anti_air_dice_mode: if include_anti_air {
+                DiceMode::LowLuck
+            } else {
+                DiceMode::Standard
+            },
+            general_firing_order: FiringOrder::Simultaneous,
+            firer_chooses_casualties: false,
+        }
+    }
+}
 
 #[derive(Debug)]
 struct Context {
@@ -86,6 +114,11 @@ impl calc::RollSelector<BattlePhase, Unit, Hit> for RollSelector {
                 let hit = unit.hit();
                 if hit == Hit::NotSubmarines && context.friendly_anti_sub {
                     Hit::AllUnits
+                } else if hit == Hit::NotSubmarines
+                    && self.firer_chooses_casualties
+                    && current_combat == BattlePhase::General
+                {
+                    Hit::Targeted
                 } else {
                     hit
                 }
@@ -102,4 +135,20 @@ impl calc::RollSelector<BattlePhase, Unit, Hit> for RollSelector {
         }
         rolls.build()
     }
+
+    fn dice_mode(&self, context: &calc::CombatContext<BattlePhase, Unit>) -> DiceMode {
+        if context.combat.battle_phase == BattlePhase::AntiAir {
+            self.anti_air_dice_mode
+        } else {
+            self.dice_mode
+        }
+    }
+
+    fn firing_order(&self, combat: &calc::Combat<BattlePhase, Unit>) -> FiringOrder {
+        if combat.battle_phase == BattlePhase::General {
+            self.general_firing_order
+        } else {
+            FiringOrder::Simultaneous
+        }
+    }
 }
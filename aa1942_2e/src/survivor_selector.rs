@@ -1,13 +1,52 @@
 use crate::*;
-use calc::{Force, ProbDist, QuantDistBuilder};
-use std::rc::Rc;
+use calc::{Force, ProbDist, ProbValue, QuantDistBuilder, Side};
+use std::sync::Arc;
+
+/// A minimum number of a unit to keep for as long as other casualties can be taken instead.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Reservation {
+    /// The reserved unit.
+    pub unit: Unit,
+    /// The number of the unit to keep.
+    pub count: u32,
+    /// The priority of the reservation. Once every other casualty has been taken, the
+    /// reservations are released starting with the lowest priority.
+    pub priority: u32,
+}
+
+impl Reservation {
+    /// Constructs a new `Reservation` keeping `count` of `unit`, with the given priority.
+    pub fn new(unit: Unit, count: u32, priority: u32) -> Self {
+        Reservation {
+            unit,
+            count,
+            priority,
+        }
+    }
+}
 
 pub struct SurvivorSelector {
     pub removal_order: Vec<Unit>,
-    pub reserved: Option<Unit>,
+    /// The units to keep. Reservations of equal priority are released in reverse order.
+    pub reserved: Vec<Reservation>,
 }
 
 impl SurvivorSelector {
+    /// Constructs a `SurvivorSelector` for a force fighting for `side`, removing units in the
+    /// order of `profile`.
+    pub fn from_profile(profile: CasualtyProfile, side: Side, reserved: Vec<Reservation>) -> Self {
+        SurvivorSelector {
+            removal_order: profile.removal_order(side),
+            reserved,
+        }
+    }
+
+    /// Returns the reservations of the standard attacker, who keeps a tank to take the territory.
+    pub fn default_attacker_reservations() -> Vec<Reservation> {
+        vec![Reservation::new(Unit::Tank, 1, 0)]
+    }
+
     pub fn default_attacker_order() -> Vec<Unit> {
         vec![
             Unit::Battleship,
@@ -42,7 +81,26 @@ impl SurvivorSelector {
         ]
     }
 
-    fn select_survivors(
+    /// Returns every targetable unit, with the most expensive first. Intended for a side choosing
+    /// the casualties of its hostiles.
+    ///
+    /// Units which survive their first hit come last, since hitting them destroys nothing.
+    pub fn most_expensive_order() -> Vec<Unit> {
+        let mut order: Vec<Unit> = Unit::all()
+            .iter()
+            .copied()
+            .filter(|unit| unit.is_targetable())
+            .collect();
+        order.sort_by_key(|unit| {
+            (
+                unit.damaged().is_some(),
+                std::cmp::Reverse(calc::Unit::ipc(*unit)),
+            )
+        });
+        order
+    }
+
+    pub(crate) fn select_survivors(
         &self,
         candidates: &QuantDist<Unit>,
         hits: &QuantDist<Hit>,
@@ -50,21 +108,29 @@ impl SurvivorSelector {
         let mut survivors: QuantDistBuilder<Unit> = candidates.clone().into();
         for hit in &Hit::order() {
             let mut count = hits.count(hit);
-            count -= self.remove_dead(&mut survivors, *hit, count, self.reserved);
-            // If any are left, take the reseved unit as well
-            if count > 0 && self.reserved.is_some() {
-                self.remove_dead(&mut survivors, *hit, count, None);
+            let mut reserved = self.reserved_by_priority();
+            count -= self.remove_dead(&mut survivors, *hit, count, &reserved);
+            // If any are left, release the reservations one by one, lowest priority first
+            while count > 0 && reserved.pop().is_some() {
+                count -= self.remove_dead(&mut survivors, *hit, count, &reserved);
             }
         }
         survivors.build()
     }
 
+    /// Returns the reservations ordered from the last to be released to the first.
+    fn reserved_by_priority(&self) -> Vec<Reservation> {
+        let mut reserved = self.reserved.clone();
+        reserved.sort_by_key(|reservation| std::cmp::Reverse(reservation.priority));
+        reserved
+    }
+
     fn remove_dead(
         &self,
         candidates: &mut QuantDistBuilder<Unit>,
         hit: Hit,
         count: u32,
-        reserved: Option<Unit>,
+        reserved: &[Reservation],
     ) -> u32 {
         let mut count = count;
         let mut total_removed = 0;
@@ -75,23 +141,16 @@ impl SurvivorSelector {
                 continue;
             }
 
-            // If the current unit is reserved, keep at least one of it
-            let remove_count = match reserved {
-                Some(reserved) if reserved == *unit => {
-                    let candidate_count = candidates.count(&unit);
-                    std::cmp::min(
-                        count,
-                        if candidate_count > 1 {
-                            candidate_count - 1
-                        } else {
-                            0
-                        },
-                    )
-                }
-                _ => count,
-            };
-
-            let removed = candidates.remove(&unit, remove_count);
+            // If the current unit is reserved, keep at least the reserved count of it
+            let reserved_count: u32 = reserved
+                .iter()
+                .filter(|reservation| reservation.unit == *unit)
+                .map(|reservation| reservation.count)
+                .sum();
+            let candidate_count = candidates.count(unit);
+            let remove_count = std::cmp::min(count, candidate_count.saturating_sub(reserved_count));
+
+            let removed = candidates.remove(unit, remove_count);
             total_removed += removed;
             count -= removed;
 
@@ -107,29 +166,29 @@ impl SurvivorSelector {
         total_removed
     }
 
-    fn without_nontargetable(force: &QuantDist<Unit>) -> QuantDist<Unit> {
+    pub(crate) fn without_nontargetable(force: &QuantDist<Unit>) -> QuantDist<Unit> {
         let mut force: QuantDistBuilder<Unit> = force.clone().into();
         for unit in &Unit::all() {
             if unit.is_targetable() {
                 continue;
             }
-            force.remove_all(&unit);
+            force.remove_all(unit);
         }
         force.build()
     }
 }
 
-impl calc::SurvivorSelector<Unit, Hit> for SurvivorSelector {
+impl<TProb: ProbValue> calc::SurvivorSelector<Unit, Hit, TProb> for SurvivorSelector {
     fn select(
         &self,
         starting_force: &QuantDist<Unit>,
-        hit_dists: &ProbDist<QuantDist<Hit>>,
-    ) -> ProbDist<Force<Unit>> {
-        let mut result = ProbDistBuilder::<Force<Unit>>::new();
+        hit_dists: &ProbDist<QuantDist<Hit>, TProb>,
+    ) -> ProbDist<Force<Unit>, TProb> {
+        let mut result = ProbDistBuilder::<Force<Unit>, TProb>::new();
         let starting_force = &Self::without_nontargetable(starting_force);
         for hit_dist in hit_dists.outcomes() {
             let survivors = self.select_survivors(starting_force, &hit_dist.item);
-            result.add(Rc::new(survivors), hit_dist.p);
+            result.add(Arc::new(survivors), hit_dist.p.clone());
         }
         result.build()
     }
@@ -168,6 +168,23 @@ impl calc::Unit for Unit {
             Unit::BattleshipDamaged => 4,
         }
     }
+
+    fn can_capture(self) -> bool {
+        match self {
+            Unit::Infantry | Unit::Artillery | Unit::Tank => true,
+            Unit::AntiAir
+            | Unit::BombardingCruiser
+            | Unit::BombardingBattleship
+            | Unit::Fighter
+            | Unit::Bomber
+            | Unit::Submarine
+            | Unit::Destroyer
+            | Unit::Cruiser
+            | Unit::Carrier
+            | Unit::Battleship
+            | Unit::BattleshipDamaged => false,
+        }
+    }
 }
 
 impl core::fmt::Display for Unit {
@@ -72,4 +72,32 @@ impl<TBattlePhase: BattlePhase> PhaseSequence<TBattlePhase> {
         let index = index - self.start.len();
         self.cycle[index % self.cycle.len()]
     }
+
+    /// Returns the number of full cycles that have completed by the end of the indicated round
+    /// index.
+    pub fn cycles_completed(&self, index: usize) -> usize {
+        if index <= self.start.len() {
+            return 0;
+        }
+        (index - self.start.len()) / self.cycle.len()
+    }
+
+    /// Returns the game round that the indicated round index belongs to.
+    ///
+    /// Players count a full cycle of the sequence as one round of combat, with the phases in
+    /// `start` taking place during the first. The pre-battle round belongs to game round 0.
+    pub fn game_round_at(&self, index: usize) -> usize {
+        if index == 0 {
+            return 0;
+        }
+        if index <= self.start.len() {
+            return 1;
+        }
+        (index - self.start.len() - 1) / self.cycle.len() + 1
+    }
+
+    /// Indicates whether or not the round at the indicated index is the last phase of a cycle.
+    pub fn is_cycle_end(&self, index: usize) -> bool {
+        index > self.start.len() && (index - self.start.len()).is_multiple_of(self.cycle.len())
+    }
 }
@@ -0,0 +1,133 @@
+use crate::*;
+use std::collections::BTreeMap;
+
+/// The expected number of units of each type lost by each side.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct Casualties<TUnit: Unit> {
+    /// The expected number of attacking units lost, by type.
+    pub attackers: BTreeMap<TUnit, f64>,
+    /// The expected number of defending units lost, by type.
+    pub defenders: BTreeMap<TUnit, f64>,
+}
+
+impl<TUnit: Unit> Casualties<TUnit> {
+    /// Constructs the expected casualties of a combat from the forces before the combat and the
+    /// distributions of forces which could survive it.
+    pub fn from_survivors<TProb: ProbValue>(
+        attackers: &Force<TUnit>,
+        surviving_attackers: &ProbDist<Force<TUnit>, TProb>,
+        defenders: &Force<TUnit>,
+        surviving_defenders: &ProbDist<Force<TUnit>, TProb>,
+    ) -> Self {
+        Casualties {
+            attackers: expected_losses(attackers, surviving_attackers),
+            defenders: expected_losses(defenders, surviving_defenders),
+        }
+    }
+
+    /// Constructs the casualties of a combat which ended with the given survivors.
+    pub fn from_outcome(
+        attackers: &Force<TUnit>,
+        surviving_attackers: &Force<TUnit>,
+        defenders: &Force<TUnit>,
+        surviving_defenders: &Force<TUnit>,
+    ) -> Self {
+        Casualties {
+            attackers: losses(attackers, surviving_attackers),
+            defenders: losses(defenders, surviving_defenders),
+        }
+    }
+
+    /// Gets the expected number of units lost by the given side, by type.
+    pub fn side(&self, side: Side) -> &BTreeMap<TUnit, f64> {
+        match side {
+            Side::Attacker => &self.attackers,
+            Side::Defender => &self.defenders,
+        }
+    }
+
+    /// Gets the expected IPC value of the units lost by the given side.
+    pub fn ipc(&self, side: Side) -> f64 {
+        self.side(side)
+            .iter()
+            .map(|(unit, count)| unit.ipc() as f64 * count)
+            .sum()
+    }
+
+    /// Adds the casualties in `other` to these, with each multiplied by `weight`.
+    pub fn add_scaled(&mut self, other: &Self, weight: f64) {
+        for (unit, count) in &other.attackers {
+            *self.attackers.entry(*unit).or_insert(0.0) += count * weight;
+        }
+        for (unit, count) in &other.defenders {
+            *self.defenders.entry(*unit).or_insert(0.0) += count * weight;
+        }
+    }
+}
+
+impl<TUnit: Unit> Default for Casualties<TUnit> {
+    fn default() -> Self {
+        Casualties {
+            attackers: BTreeMap::new(),
+            defenders: BTreeMap::new(),
+        }
+    }
+}
+
+/// Adds the casualties of each phase in `other` to those in `casualties`, with each multiplied
+/// by `weight`.
+pub fn add_phase_casualties<TBattlePhase: BattlePhase, TUnit: Unit>(
+    casualties: &mut BTreeMap<TBattlePhase, Casualties<TUnit>>,
+    other: &BTreeMap<TBattlePhase, Casualties<TUnit>>,
+    weight: f64,
+) {
+    for (battle_phase, other) in other {
+        casualties
+            .entry(*battle_phase)
+            .or_default()
+            .add_scaled(other, weight);
+    }
+}
+
+/// The expected number of units of each type in `force` which don't survive.
+fn expected_losses<TUnit: Unit, TProb: ProbValue>(
+    force: &Force<TUnit>,
+    survivors: &ProbDist<Force<TUnit>, TProb>,
+) -> BTreeMap<TUnit, f64> {
+    let total_p = survivors.total_p().to_f64();
+    let mut losses = BTreeMap::new();
+    if total_p == 0.0 {
+        return losses;
+    }
+    for quant in force.outcomes() {
+        let lost: f64 = survivors
+            .outcomes()
+            .iter()
+            .map(|survivor| {
+                let lost = quant.count.saturating_sub(survivor.item.count(&quant.item));
+                lost as f64 * survivor.p.to_f64()
+            })
+            .sum();
+        if lost > 0.0 {
+            losses.insert(quant.item, lost / total_p);
+        }
+    }
+    losses
+}
+
+/// The number of units of each type in `force` which aren't among `survivors`.
+fn losses<TUnit: Unit>(force: &Force<TUnit>, survivors: &Force<TUnit>) -> BTreeMap<TUnit, f64> {
+    force
+        .outcomes()
+        .iter()
+        .filter_map(|quant| {
+            let lost = quant.count.saturating_sub(survivors.count(&quant.item));
+            if lost > 0 {
+                Some((quant.item, lost as f64))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,121 @@
+use crate::stats::Summarizer;
+use crate::*;
+use std::fmt;
+
+/// The version of the checkpoint format, which is incremented whenever the contents of a
+/// checkpoint change.
+pub const CHECKPOINT_VERSION: u32 = 1;
+
+/// The reason a checkpoint couldn't be resumed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckpointError {
+    /// The checkpoint was saved in a different version of the format.
+    UnsupportedVersion(u32),
+}
+
+impl fmt::Display for CheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckpointError::UnsupportedVersion(version) => write!(
+                f,
+                "Checkpoint version {} is not supported, expected version {}.",
+                version, CHECKPOINT_VERSION
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CheckpointError {}
+
+/// Checks that a checkpoint was saved in the current version of the format.
+fn check_version(version: u32) -> Result<(), CheckpointError> {
+    if version == CHECKPOINT_VERSION {
+        Ok(())
+    } else {
+        Err(CheckpointError::UnsupportedVersion(version))
+    }
+}
+
+/// The version of a checkpoint, which can be deserialized from any checkpoint regardless of the
+/// rest of its contents.
+///
+/// A checkpoint saved in another version of the format may not deserialize at all, so its
+/// version should be deserialized and checked first, before the rest of it.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CheckpointVersion {
+    /// The version of the checkpoint format.
+    pub version: u32,
+}
+
+impl CheckpointVersion {
+    /// Checks that a checkpoint of this version can be resumed.
+    pub fn check(&self) -> Result<(), CheckpointError> {
+        check_version(self.version)
+    }
+}
+
+/// A snapshot of a `RoundManager` part way through a battle, from which it can be resumed.
+///
+/// The combat manager, phase sequence, and retreat policy aren't included, and must be provided
+/// again when resuming.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone)]
+pub struct RoundManagerCheckpoint<
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    TProb: ProbValue = Probability,
+> {
+    /// The version of the checkpoint format.
+    pub version: u32,
+    /// The index of the last round computed.
+    pub round_index: usize,
+    /// The number of rounds after which any pending combats are cut off, and counted as pruned.
+    pub round_limit: usize,
+    /// Whether or not the most likely predecessor of each combat is tracked.
+    pub track_predecessors: bool,
+    /// The settings of the pruning strategy, or None if the strategy can't be saved.
+    pub pruning: Option<PruningSettings<TProb>>,
+    /// The result of the last round computed.
+    pub last_round: RoundResult<TBattlePhase, TUnit, TProb>,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    RoundManagerCheckpoint<TBattlePhase, TUnit, TProb>
+{
+    /// Checks that this checkpoint can be resumed.
+    pub fn check_version(&self) -> Result<(), CheckpointError> {
+        check_version(self.version)
+    }
+}
+
+/// A snapshot of a `Summarizer` part way through a battle, from which it can be resumed.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SummarizerCheckpoint<
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    TProb: ProbValue = Probability,
+> {
+    /// The version of the checkpoint format.
+    pub version: u32,
+    /// The summarizer.
+    pub summarizer: Summarizer<TBattlePhase, TUnit, TProb>,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    SummarizerCheckpoint<TBattlePhase, TUnit, TProb>
+{
+    /// Constructs a checkpoint of `summarizer`.
+    pub fn new(summarizer: &Summarizer<TBattlePhase, TUnit, TProb>) -> Self {
+        SummarizerCheckpoint {
+            version: CHECKPOINT_VERSION,
+            summarizer: summarizer.clone(),
+        }
+    }
+
+    /// Checks that this checkpoint can be resumed.
+    pub fn check_version(&self) -> Result<(), CheckpointError> {
+        check_version(self.version)
+    }
+}
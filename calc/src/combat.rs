@@ -31,10 +31,37 @@ where
         }
     }
 
+    /// Indicates whether or not the attackers won the combat with a unit able to capture the
+    /// territory.
+    pub fn captured(&self) -> bool {
+        self.winner() == Some(Side::Attacker)
+            && self
+                .attackers
+                .outcomes()
+                .iter()
+                .any(|quant| quant.item.can_capture())
+    }
+
     /// Indicates whether or not the combat is considered complete.
     pub fn completed(&self) -> bool {
         self.attackers.is_empty() || self.defenders.is_empty()
     }
+
+    /// Returns a copy of this combat with the force of `side` replaced by `force`.
+    pub fn with_force(&self, side: Side, force: Force<TUnit>) -> Self {
+        match side {
+            Side::Attacker => Combat {
+                battle_phase: self.battle_phase,
+                attackers: force,
+                defenders: self.defenders.clone(),
+            },
+            Side::Defender => Combat {
+                battle_phase: self.battle_phase,
+                attackers: self.attackers.clone(),
+                defenders: force,
+            },
+        }
+    }
 }
 
 /// Context of a combat used for selecting rolls.
@@ -79,17 +106,27 @@ where
 
 /// The result of a combat.
 #[derive(Debug)]
-pub struct CombatResult<TBattlePhase, TUnit>
+pub struct CombatResult<TBattlePhase, TUnit, TProb = Probability>
 where
     TBattlePhase: BattlePhase,
     TUnit: Unit,
+    TProb: ProbValue,
 {
     /// The phase of battle the combat took place in.
     pub battle_phase: TBattlePhase,
+    /// The combat which was resolved.
+    pub combat: Combat<TBattlePhase, TUnit>,
     /// A `ProbDist` of the attackers who could have survived the combat.
-    pub surviving_attackers: ProbDist<Force<TUnit>>,
+    pub surviving_attackers: ProbDist<Force<TUnit>, TProb>,
     /// A `ProbDist` of the defenders who could have survived the combat.
-    pub surviving_defenders: ProbDist<Force<TUnit>>,
+    pub surviving_defenders: ProbDist<Force<TUnit>, TProb>,
+    /// A `ProbDist` of each pair of attackers and defenders who could have survived the combat.
+    /// When both sides fire simultaneously, this is the product of `surviving_attackers` and
+    /// `surviving_defenders`, but when one side fires first, the survivors of the other side
+    /// depend on which of its targets survived.
+    pub surviving: ProbDist<(Force<TUnit>, Force<TUnit>), TProb>,
     /// The probability that the combat occurrs at all.
-    pub probability: Probability,
+    pub probability: TProb,
+    /// The expected casualties of each side, given that the combat occurs.
+    pub casualties: Casualties<TUnit>,
 }
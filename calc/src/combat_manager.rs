@@ -1,5 +1,5 @@
 use crate::*;
-use std::marker::PhantomData;
+use std::{hash::Hash, marker::PhantomData};
 
 /// Manages the resolution of individual combats.
 pub struct CombatManager<
@@ -7,25 +7,29 @@ pub struct CombatManager<
     TUnit: Unit,
     THit: Hit<TUnit>,
     TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
-    TSurvivorSelector: SurvivorSelector<TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue = Probability,
 > {
     attacker_survivor_selector: TSurvivorSelector,
     defender_survivor_selector: TSurvivorSelector,
+    attacker_target_selector: Option<TSurvivorSelector>,
+    defender_target_selector: Option<TSurvivorSelector>,
     roll_selector: TRollSelector,
-    roller: Roller<TUnit, THit>,
+    roller: Roller<TUnit, THit, TProb>,
     phantom_battle_phase: PhantomData<TBattlePhase>,
     phantom_hit: PhantomData<THit>,
     phantom_unit: PhantomData<TUnit>,
 }
 
-impl<TBattlePhase, THit, TUnit, TRollSelector, TSurvivorSelector>
-    CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector>
+impl<TBattlePhase, THit, TUnit, TRollSelector, TSurvivorSelector, TProb>
+    CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>
 where
     TBattlePhase: BattlePhase,
     TUnit: Unit,
     THit: Hit<TUnit>,
     TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
-    TSurvivorSelector: SurvivorSelector<TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue,
 {
     /// Constructs a new combat manager with the given survivor selectors and roll selectors.
     pub fn new(
@@ -36,6 +40,8 @@ where
         CombatManager {
             attacker_survivor_selector,
             defender_survivor_selector,
+            attacker_target_selector: None,
+            defender_target_selector: None,
             roll_selector,
             phantom_battle_phase: PhantomData,
             phantom_hit: PhantomData,
@@ -44,38 +50,332 @@ where
         }
     }
 
+    /// Sets the selectors with which each side chooses the casualties of the other side, for
+    /// hits allocated by the firing side. Without them, the receiving side chooses its own
+    /// casualties for every hit. A target selector is given the context of the receiving side.
+    pub fn set_target_selectors(
+        &mut self,
+        attacker_target_selector: TSurvivorSelector,
+        defender_target_selector: TSurvivorSelector,
+    ) {
+        self.attacker_target_selector = Some(attacker_target_selector);
+        self.defender_target_selector = Some(defender_target_selector);
+    }
+
     /// Resolves a combat into a combat result.
     pub fn resolve(
-        &mut self,
-        combat: &Prob<Combat<TBattlePhase, TUnit>>,
-    ) -> CombatResult<TBattlePhase, TUnit> {
-        let probability = combat.p;
+        &self,
+        combat: &Prob<Combat<TBattlePhase, TUnit>, TProb>,
+    ) -> CombatResult<TBattlePhase, TUnit, TProb> {
+        let probability = combat.p.clone();
         let combat = &combat.item;
-        let attackers = &combat.attackers;
-        let defenders = &combat.defenders;
 
-        let attack_context = CombatContext::from_combat(combat, Side::Attacker);
-        let defense_context = CombatContext::from_combat(combat, Side::Defender);
+        let (surviving_attackers, surviving_defenders, surviving) =
+            match self.roll_selector.firing_order(combat) {
+                FiringOrder::Simultaneous => {
+                    let surviving_attackers = self.fire(combat, Side::Defender);
+                    let surviving_defenders = self.fire(combat, Side::Attacker);
+                    let mut surviving =
+                        Vec::with_capacity(surviving_attackers.len() * surviving_defenders.len());
+                    for attacker in surviving_attackers.outcomes() {
+                        for defender in surviving_defenders.outcomes() {
+                            surviving.push(Prob::new(
+                                (attacker.item.clone(), defender.item.clone()),
+                                attacker.p.clone() * defender.p.clone(),
+                            ));
+                        }
+                    }
+                    (
+                        surviving_attackers,
+                        surviving_defenders,
+                        ProbDist::from_distinct(surviving),
+                    )
+                }
+                FiringOrder::AttackerFirst => self.fire_sequentially(combat, Side::Attacker),
+                FiringOrder::DefenderFirst => self.fire_sequentially(combat, Side::Defender),
+            };
 
-        let attack_strike = self.roll_selector.get_rolls(&attack_context);
-        let defense_strike = self.roll_selector.get_rolls(&defense_context);
+        let casualties = Casualties::from_survivors(
+            &combat.attackers,
+            &surviving_attackers,
+            &combat.defenders,
+            &surviving_defenders,
+        );
+        CombatResult {
+            battle_phase: combat.battle_phase,
+            combat: combat.clone(),
+            surviving_attackers,
+            surviving_defenders,
+            surviving,
+            probability,
+            casualties,
+        }
+    }
+
+    /// Rolls the hits of `side` in `combat`, and selects the hostile forces which could survive
+    /// them.
+    fn fire(
+        &self,
+        combat: &Combat<TBattlePhase, TUnit>,
+        side: Side,
+    ) -> ProbDist<Force<TUnit>, TProb> {
+        let context = CombatContext::from_combat(combat, side);
+        let strike = self.roll_selector.get_rolls(&context);
+        let mode = self.roll_selector.dice_mode(&context);
+        let hits = self.roller.roll_hits(strike, mode);
+        self.select(combat, side, &hits)
+    }
 
-        let defending_hits = self.roller.roll_hits(defense_strike);
+    /// Selects the hostile forces which could survive the hits of `side` in `combat`.
+    ///
+    /// Hits allocated by the firing side are applied first, using the target selector of the
+    /// firing side, and the receiving side then chooses its casualties for the remaining hits.
+    fn select(
+        &self,
+        combat: &Combat<TBattlePhase, TUnit>,
+        side: Side,
+        hits: &ProbDist<QuantDist<THit>, TProb>,
+    ) -> ProbDist<Force<TUnit>, TProb> {
+        let (hostile_side, receiver, firer) = match side {
+            Side::Attacker => (
+                Side::Defender,
+                &self.defender_survivor_selector,
+                &self.attacker_target_selector,
+            ),
+            Side::Defender => (
+                Side::Attacker,
+                &self.attacker_survivor_selector,
+                &self.defender_target_selector,
+            ),
+        };
+        let firer = match firer {
+            Some(firer)
+                if hits
+                    .outcomes()
+                    .iter()
+                    .flat_map(|hits| hits.item.outcomes())
+                    .any(|quant| quant.item.allocator() == Allocator::Firer) =>
+            {
+                firer
+            }
+            _ => {
+                return receiver.select_in(&CombatContext::from_combat(combat, hostile_side), hits)
+            }
+        };
 
-        let surviving_attackers = self
-            .attacker_survivor_selector
-            .select(attackers, &defending_hits);
+        let mut survivors = ProbDistBuilder::new();
+        for outcome in hits.outcomes() {
+            let mut chosen = QuantDistBuilder::new();
+            let mut received = QuantDistBuilder::new();
+            for quant in outcome.item.outcomes() {
+                match quant.item.allocator() {
+                    Allocator::Firer => chosen.add_quant(*quant),
+                    Allocator::Receiver => received.add_quant(*quant),
+                }
+            }
+            let chosen = certain(chosen.build());
+            let received = certain(received.build());
+            let hostile_context = CombatContext::from_combat(combat, hostile_side);
+            for remaining in firer.select_in(&hostile_context, &chosen).into_outcomes() {
+                let remaining_combat = combat.with_force(hostile_side, remaining.item);
+                let context = CombatContext::from_combat(&remaining_combat, hostile_side);
+                for survivor in receiver.select_in(&context, &received).into_outcomes() {
+                    let p = outcome.p.clone() * remaining.p.clone() * survivor.p;
+                    survivors.add(survivor.item, p);
+                }
+            }
+        }
+        survivors.build()
+    }
 
-        let attacking_hits = self.roller.roll_hits(attack_strike);
-        let surviving_defenders = self
-            .defender_survivor_selector
-            .select(defenders, &attacking_hits);
+    /// Resolves `combat` with `first` firing and removing its casualties before the other side
+    /// fires back with whatever survived, returning the surviving attackers, the surviving
+    /// defenders, and the surviving pairs.
+    #[allow(clippy::type_complexity)]
+    fn fire_sequentially(
+        &self,
+        combat: &Combat<TBattlePhase, TUnit>,
+        first: Side,
+    ) -> (
+        ProbDist<Force<TUnit>, TProb>,
+        ProbDist<Force<TUnit>, TProb>,
+        ProbDist<(Force<TUnit>, Force<TUnit>), TProb>,
+    ) {
+        let targets = self.fire(combat, first);
+        let mut first_survivors = ProbDistBuilder::new();
+        let mut surviving = Vec::new();
+        for target in targets.outcomes() {
+            let (remaining, second) = match first {
+                Side::Attacker => (
+                    Combat {
+                        defenders: target.item.clone(),
+                        ..combat.clone()
+                    },
+                    Side::Defender,
+                ),
+                Side::Defender => (
+                    Combat {
+                        attackers: target.item.clone(),
+                        ..combat.clone()
+                    },
+                    Side::Attacker,
+                ),
+            };
+            for survivor in self.fire(&remaining, second).into_outcomes() {
+                let p = target.p.clone() * survivor.p;
+                first_survivors.add(survivor.item.clone(), p.clone());
+                let pair = match first {
+                    Side::Attacker => (survivor.item, target.item.clone()),
+                    Side::Defender => (target.item.clone(), survivor.item),
+                };
+                surviving.push(Prob::new(pair, p));
+            }
+        }
+
+        let first_survivors = first_survivors.build();
+        match first {
+            Side::Attacker => (first_survivors, targets, ProbDist::from_distinct(surviving)),
+            Side::Defender => (targets, first_survivors, ProbDist::from_distinct(surviving)),
+        }
+    }
 
+    /// Indicates whether or not the combat can never complete, because neither side can hit the
+    /// other in any phase of the `cycle` of `sequence`.
+    pub fn is_stalemated(
+        &self,
+        combat: &Combat<TBattlePhase, TUnit>,
+        sequence: &PhaseSequence<TBattlePhase>,
+    ) -> bool {
+        sequence.cycle().iter().all(|battle_phase| {
+            let combat = Combat {
+                battle_phase: *battle_phase,
+                attackers: combat.attackers.clone(),
+                defenders: combat.defenders.clone(),
+            };
+            !self.can_hit(&combat, Side::Attacker) && !self.can_hit(&combat, Side::Defender)
+        })
+    }
+
+    /// Indicates whether or not `side` has any roll that could hit a hostile unit.
+    fn can_hit(&self, combat: &Combat<TBattlePhase, TUnit>, side: Side) -> bool {
+        let context = CombatContext::from_combat(combat, side);
+        let hostiles = context.hostiles();
+        self.roll_selector
+            .get_rolls(&context)
+            .outcomes()
+            .iter()
+            .filter(|quant| quant.count > 0 && quant.item.successes() > 0)
+            .any(|quant| {
+                hostiles
+                    .outcomes()
+                    .iter()
+                    .any(|hostile| quant.item.hit.hits(hostile.item))
+            })
+    }
+
+    /// Resolves a combat by rolling the dice, rather than enumerating every possible outcome.
+    ///
+    /// The returned combat result contains exactly one surviving attacking force and one
+    /// surviving defending force, each with a probability of one.
+    pub fn sample<R: rand::Rng + ?Sized>(
+        &self,
+        combat: &Prob<Combat<TBattlePhase, TUnit>, TProb>,
+        rng: &mut R,
+    ) -> CombatResult<TBattlePhase, TUnit, TProb> {
+        let probability = combat.p.clone();
+        let combat = &combat.item;
+
+        let (surviving_attackers, surviving_defenders) =
+            match self.roll_selector.firing_order(combat) {
+                FiringOrder::Simultaneous => {
+                    let surviving_attackers = self.sample_fire(combat, Side::Defender, rng);
+                    let surviving_defenders = self.sample_fire(combat, Side::Attacker, rng);
+                    (surviving_attackers, surviving_defenders)
+                }
+                FiringOrder::AttackerFirst => {
+                    let surviving_defenders = self.sample_fire(combat, Side::Attacker, rng);
+                    let remaining = Combat {
+                        defenders: surviving_defenders.clone(),
+                        ..combat.clone()
+                    };
+                    let surviving_attackers = self.sample_fire(&remaining, Side::Defender, rng);
+                    (surviving_attackers, surviving_defenders)
+                }
+                FiringOrder::DefenderFirst => {
+                    let surviving_attackers = self.sample_fire(combat, Side::Defender, rng);
+                    let remaining = Combat {
+                        attackers: surviving_attackers.clone(),
+                        ..combat.clone()
+                    };
+                    let surviving_defenders = self.sample_fire(&remaining, Side::Attacker, rng);
+                    (surviving_attackers, surviving_defenders)
+                }
+            };
+
+        let surviving = certain((surviving_attackers.clone(), surviving_defenders.clone()));
+        let surviving_attackers = certain(surviving_attackers);
+        let surviving_defenders = certain(surviving_defenders);
+        let casualties = Casualties::from_survivors(
+            &combat.attackers,
+            &surviving_attackers,
+            &combat.defenders,
+            &surviving_defenders,
+        );
         CombatResult {
             battle_phase: combat.battle_phase,
+            combat: combat.clone(),
             surviving_attackers,
             surviving_defenders,
+            surviving,
             probability,
+            casualties,
+        }
+    }
+
+    /// Rolls the dice for `side` in `combat`, and randomly chooses which hostile force survives.
+    fn sample_fire<R: rand::Rng + ?Sized>(
+        &self,
+        combat: &Combat<TBattlePhase, TUnit>,
+        side: Side,
+        rng: &mut R,
+    ) -> Force<TUnit> {
+        let context = CombatContext::from_combat(combat, side);
+        let strike = self.roll_selector.get_rolls(&context);
+        let mode = self.roll_selector.dice_mode(&context);
+        let hits = certain(roll_dice(&strike, mode, rng));
+        choose(self.select(combat, side, &hits), rng)
+    }
+}
+
+/// Rolls the dice for `strike` under the given dice mode.
+fn roll_dice<TUnit: Unit, THit: Hit<TUnit>, R: rand::Rng + ?Sized>(
+    strike: &QuantDist<Roll<TUnit, THit>>,
+    mode: DiceMode,
+    rng: &mut R,
+) -> QuantDist<THit> {
+    match mode {
+        DiceMode::Standard => sample_hits(strike, rng),
+        DiceMode::LowLuck => sample_hits_low_luck(strike, rng),
+    }
+}
+
+/// Constructs a distribution containing only `item`.
+fn certain<T: Eq + Hash, TProb: ProbValue>(item: T) -> ProbDist<T, TProb> {
+    vec![Prob::new(item, TProb::one())].into()
+}
+
+/// Randomly chooses an outcome from `dist`, weighted by probability.
+fn choose<T, TProb: ProbValue, R: rand::Rng + ?Sized>(dist: ProbDist<T, TProb>, rng: &mut R) -> T {
+    let total: f64 = dist.outcomes().iter().map(|o| o.p.to_f64()).sum();
+    let mut target = rng.gen::<f64>() * total;
+    let mut outcomes = dist.into_outcomes();
+    let last = outcomes.pop().expect("Distribution must not be empty.");
+    for outcome in outcomes {
+        let p = outcome.p.to_f64();
+        if target < p {
+            return outcome.item;
         }
+        target -= p;
     }
+    last.item
 }
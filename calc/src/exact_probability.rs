@@ -0,0 +1,192 @@
+use crate::ProbValue;
+use core::fmt::Display;
+use core::iter::{Iterator, Sum};
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub, SubAssign};
+use num_bigint::BigInt;
+use num_rational::BigRational;
+use num_traits::{One, ToPrimitive, Zero};
+
+/// The exact probability of an event occurring, represented as an arbitrary precision rational.
+/// Guaranteed to lie within [0, 1].
+///
+/// Unlike `Probability`, no rounding error is ever introduced, at the cost of speed and memory.
+/// Note that with a prune threshold of zero, battles that may continue indefinitely will
+/// never complete, since the probability of continuing never reaches exactly zero.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ExactProbability {
+    value: BigRational,
+}
+
+impl ExactProbability {
+    /// Construct a new `ExactProbability` with a probability of `value`.
+    ///
+    /// Panics if `value` is less than 0 or greater than 1.
+    pub fn new(value: BigRational) -> Self {
+        if value < BigRational::zero() || value > BigRational::one() {
+            panic!("Probability value must be between 0 and 1.");
+        }
+        Self { value }
+    }
+
+    /// Returns the rational value of this probability.
+    pub fn ratio(&self) -> &BigRational {
+        &self.value
+    }
+}
+
+impl ProbValue for ExactProbability {
+    fn zero() -> Self {
+        Self {
+            value: BigRational::zero(),
+        }
+    }
+
+    fn one() -> Self {
+        Self {
+            value: BigRational::one(),
+        }
+    }
+
+    fn from_ratio(numer: u32, denom: u32) -> Self {
+        if numer > denom {
+            panic!(
+                "Numerator must be less than or equal to denominator. {} / {}",
+                numer, denom
+            );
+        }
+        if denom == 0 {
+            panic!("Denominator must be non-zero.");
+        }
+        Self {
+            value: BigRational::new(numer.into(), denom.into()),
+        }
+    }
+
+    fn binomial(numer: u32, denom: u32, trials: u32) -> Vec<Self> {
+        if numer > denom || denom == 0 {
+            panic!("Invalid success ratio: {} / {}", numer, denom);
+        }
+
+        // P(k) = C(n, k) * numer^k * (denom - numer)^(n - k) / denom^n
+        let success = BigInt::from(numer);
+        let failure = BigInt::from(denom - numer);
+        let total = num_traits::pow(BigInt::from(denom), trials as usize);
+        let mut coefficient = BigInt::one();
+        let mut masses = Vec::with_capacity(trials as usize + 1);
+        for successes in 0..=trials {
+            let failures = trials - successes;
+            let numer = &coefficient
+                * num_traits::pow(success.clone(), successes as usize)
+                * num_traits::pow(failure.clone(), failures as usize);
+            masses.push(Self {
+                value: BigRational::new(numer, total.clone()),
+            });
+            coefficient = coefficient * BigInt::from(failures) / BigInt::from(successes + 1);
+        }
+        masses
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.value.to_f64().unwrap_or(0.0)
+    }
+}
+
+impl Sum for ExactProbability {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::zero(), Add::add)
+    }
+}
+
+impl Default for ExactProbability {
+    fn default() -> Self {
+        Self::zero()
+    }
+}
+
+impl Add<ExactProbability> for ExactProbability {
+    type Output = Self;
+
+    fn add(self, rhs: ExactProbability) -> Self::Output {
+        let value = self.value + rhs.value;
+        if value > BigRational::one() {
+            panic!("Add results in a probability greater than 1: {}", value);
+        }
+        Self { value }
+    }
+}
+
+impl AddAssign for ExactProbability {
+    fn add_assign(&mut self, rhs: Self) {
+        self.value += rhs.value;
+        if self.value > BigRational::one() {
+            panic!(
+                "Add results in a probability greater than 1: {}",
+                self.value
+            );
+        }
+    }
+}
+
+impl Sub<ExactProbability> for ExactProbability {
+    type Output = Self;
+
+    fn sub(self, rhs: ExactProbability) -> Self::Output {
+        let value = self.value - rhs.value;
+        if value < BigRational::zero() {
+            panic!("Sub results in a probability less than 0: {}", value);
+        }
+        Self { value }
+    }
+}
+
+impl SubAssign for ExactProbability {
+    fn sub_assign(&mut self, rhs: Self) {
+        self.value -= rhs.value;
+        if self.value < BigRational::zero() {
+            panic!("Sub results in a probability less than 0: {}", self.value);
+        }
+    }
+}
+
+impl Mul<ExactProbability> for ExactProbability {
+    type Output = Self;
+
+    fn mul(self, rhs: ExactProbability) -> Self::Output {
+        Self {
+            value: self.value * rhs.value,
+        }
+    }
+}
+
+impl MulAssign for ExactProbability {
+    fn mul_assign(&mut self, rhs: Self) {
+        self.value *= rhs.value;
+    }
+}
+
+impl Div<ExactProbability> for ExactProbability {
+    type Output = Self;
+
+    fn div(self, rhs: ExactProbability) -> Self::Output {
+        if rhs.value.is_zero() {
+            panic!("Division by a probability of 0.");
+        }
+        let value = self.value / rhs.value;
+        if value > BigRational::one() {
+            panic!("Div results in a probability greater than 1: {}", value);
+        }
+        Self { value }
+    }
+}
+
+impl Display for ExactProbability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        self.value.fmt(f)
+    }
+}
+
+impl From<ExactProbability> for f64 {
+    fn from(value: ExactProbability) -> Self {
+        value.to_f64()
+    }
+}
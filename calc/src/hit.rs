@@ -1,7 +1,24 @@
 use std::{fmt::Debug, hash::Hash};
 
+/// The side which chooses the casualties of a hit.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash, Default)]
+pub enum Allocator {
+    /// The side receiving the hit chooses its own casualties.
+    #[default]
+    Receiver,
+    /// The side firing the hit chooses the casualties of the other side.
+    Firer,
+}
+
 /// Represents a hit that can damage or kill certain units.
 pub trait Hit<TUnit: crate::Unit>: Debug + Clone + Copy + Eq + Ord + Hash + Sized {
     /// Returns whether or not this hit can hit `unit`.
     fn hits(self, unit: TUnit) -> bool;
+
+    /// Returns the side which chooses the casualties of this hit. Defaults to
+    /// `Allocator::Receiver`.
+    fn allocator(self) -> Allocator {
+        Allocator::Receiver
+    }
 }
@@ -1,36 +1,52 @@
 pub mod stats;
 
 mod battle_phase;
+mod casualties;
+mod checkpoint;
 mod combat;
 mod combat_manager;
+#[cfg(feature = "exact")]
+mod exact_probability;
 mod hit;
+mod markov_solver;
 mod prob;
 mod probability;
 mod pruner;
 mod quant;
+mod retreat;
 mod roll;
 mod roller;
 mod round_manager;
 mod round_result;
+#[cfg(feature = "serde1")]
+mod serde_pairs;
+mod simulator;
 mod survivor_selector;
 mod unit;
 
 pub use battle_phase::*;
+pub use casualties::*;
+pub use checkpoint::*;
 pub use combat::*;
 pub use combat_manager::CombatManager;
-pub use hit::Hit;
+#[cfg(feature = "exact")]
+pub use exact_probability::ExactProbability;
+pub use hit::{Allocator, Hit};
+pub use markov_solver::{MarkovSolution, MarkovSolver};
 pub use prob::*;
-pub use probability::Probability;
-pub use pruner::Pruner;
+pub use probability::{ProbValue, Probability};
+pub use pruner::*;
 pub use quant::*;
+pub use retreat::*;
 pub use roll::*;
 pub use roller::Roller;
 pub use round_manager::*;
 pub use round_result::*;
+pub use simulator::Simulator;
 pub use survivor_selector::SurvivorSelector;
 pub use unit::*;
 
-pub use roller::roll_hits;
+pub use roller::{roll_hits, roll_hits_low_luck, sample_hits, sample_hits_low_luck};
 
 /// The side of combat - attacker or defender.
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
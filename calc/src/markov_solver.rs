@@ -0,0 +1,371 @@
+use crate::stats::{BattleSummary, Summarizer};
+use crate::*;
+use fnv::{FnvHashMap, FnvHashSet};
+
+/// Solves a battle exactly by treating it as an absorbing Markov chain.
+///
+/// Once the phases in `start` have occurred, the `PhaseSequence` repeats `cycle` forever, so
+/// the battle only ever visits a finite number of states: a combat at a position in the
+/// sequence. Rather than iterating round by round until the remaining probability is negligible,
+/// the solver enumerates every reachable state once and solves for the probability of ending in
+/// each completed combat.
+///
+/// Nothing is pruned, and combats that can never complete - true stalemates - are reported with
+/// their exact probability instead of being detected heuristically.
+pub struct MarkovSolver<
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    THit: Hit<TUnit>,
+    TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue = Probability,
+> {
+    combat_manager:
+        CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>,
+    sequence: PhaseSequence<TBattlePhase>,
+    attackers: Force<TUnit>,
+    defenders: Force<TUnit>,
+}
+
+/// A state of the battle - a combat pending at a position in the phase sequence.
+type State<TBattlePhase, TUnit> = (usize, Combat<TBattlePhase, TUnit>);
+
+/// The transitions out of each state, by state index.
+type Transitions<TProb> = Vec<Vec<Prob<usize, TProb>>>;
+
+impl<TBattlePhase, THit, TUnit, TRollSelector, TSurvivorSelector, TProb>
+    MarkovSolver<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>
+where
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    THit: Hit<TUnit>,
+    TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue,
+{
+    /// Constructs a new `MarkovSolver` with the given `CombatManager`, `PhaseSequence`,
+    /// attacking force, and defending force.
+    pub fn new(
+        combat_manager: CombatManager<
+            TBattlePhase,
+            TUnit,
+            THit,
+            TRollSelector,
+            TSurvivorSelector,
+            TProb,
+        >,
+        sequence: PhaseSequence<TBattlePhase>,
+        attackers: Force<TUnit>,
+        defenders: Force<TUnit>,
+    ) -> Self {
+        MarkovSolver {
+            combat_manager,
+            sequence,
+            attackers,
+            defenders,
+        }
+    }
+
+    /// Computes the probability of every way the battle can end.
+    pub fn solve(&self) -> MarkovSolution<TBattlePhase, TUnit, TProb> {
+        let first_position = 1;
+        let initial = Combat {
+            battle_phase: self.sequence.combat_at(first_position),
+            attackers: self.attackers.clone(),
+            defenders: self.defenders.clone(),
+        };
+        let (states, transitions) = self.explore((first_position, initial));
+
+        let mut mass = vec![TProb::zero(); states.len()];
+        mass[0] = TProb::one();
+        let mut stalemated = Vec::new();
+        // Components are found in reverse topological order, and probability only flows from a
+        // component to those after it in topological order.
+        for component in strongly_connected_components(&transitions).iter().rev() {
+            resolve_component(component, &transitions, &mut mass, &mut stalemated);
+        }
+
+        let mut completed = ProbDistBuilder::new();
+        for (state, p) in states.iter().zip(mass.iter()) {
+            if state.1.completed() {
+                completed.add(state.1.clone(), p.clone());
+            }
+        }
+        let mut stalemated_combats = ProbDistBuilder::new();
+        for index in stalemated {
+            stalemated_combats.add(states[index].1.clone(), mass[index].clone());
+        }
+
+        MarkovSolution {
+            completed: completed.build(),
+            stalemated: stalemated_combats.build(),
+            state_count: states.len(),
+            prebattle: RoundResult::new_initial(
+                self.sequence.combat_at(first_position),
+                self.attackers.clone(),
+                self.defenders.clone(),
+            ),
+        }
+    }
+
+    /// Enumerates every state reachable from `initial`, along with the transitions out of each.
+    /// Completed combats have no transitions.
+    fn explore(
+        &self,
+        initial: State<TBattlePhase, TUnit>,
+    ) -> (Vec<State<TBattlePhase, TUnit>>, Transitions<TProb>) {
+        let mut states = vec![initial.clone()];
+        let mut indices = FnvHashMap::default();
+        indices.insert(initial, 0);
+        let mut transitions = Vec::new();
+
+        while transitions.len() < states.len() {
+            let (position, combat) = states[transitions.len()].clone();
+            if combat.completed() {
+                transitions.push(Vec::new());
+                continue;
+            }
+
+            let next_position = self.next_position(position);
+            let next_battle_phase = self.sequence.combat_at(next_position);
+            let result = self
+                .combat_manager
+                .resolve(&Prob::new(combat, TProb::one()));
+            let mut outgoing = ProbDistBuilder::new();
+            for survivors in result.surviving.outcomes() {
+                let (attackers, defenders) = &survivors.item;
+                let combat = Combat {
+                    battle_phase: next_battle_phase,
+                    attackers: attackers.clone(),
+                    defenders: defenders.clone(),
+                };
+                // A completed combat is the end of the battle no matter where it occurs.
+                let position = if combat.completed() { 0 } else { next_position };
+                let state = (position, combat);
+                let index = match indices.get(&state) {
+                    Some(index) => *index,
+                    None => {
+                        states.push(state.clone());
+                        indices.insert(state, states.len() - 1);
+                        states.len() - 1
+                    }
+                };
+                outgoing.add(index, survivors.p.clone());
+            }
+            transitions.push(outgoing.build().into_outcomes());
+        }
+        (states, transitions)
+    }
+
+    /// Returns the position in the sequence following `position`, wrapping around the cycle.
+    fn next_position(&self, position: usize) -> usize {
+        let start_len = self.sequence.start().len();
+        if position < start_len + self.sequence.cycle().len() {
+            position + 1
+        } else {
+            start_len + 1
+        }
+    }
+}
+
+/// Moves all probability entering `component` to the states it eventually ends in.
+///
+/// States are eliminated one at a time; the probability of each eliminated state, along with the
+/// transitions into it, are redistributed among its successors. This is the GTH variant of state
+/// reduction: the probability of leaving a state is computed by summing its outgoing transitions
+/// rather than by subtracting its self-transition from one, so every intermediate value is itself
+/// a probability. A state that can only transition to itself is a stalemate, and keeps whatever
+/// probability reaches it.
+fn resolve_component<TProb: ProbValue>(
+    component: &[usize],
+    transitions: &[Vec<Prob<usize, TProb>>],
+    mass: &mut [TProb],
+    stalemated: &mut Vec<usize>,
+) {
+    if let [state] = component {
+        let outgoing = &transitions[*state];
+        if outgoing.is_empty() {
+            // A completed combat, which keeps its probability.
+            return;
+        }
+        if outgoing.iter().all(|t| t.item != *state) {
+            let p = std::mem::take(&mut mass[*state]);
+            for transition in outgoing {
+                mass[transition.item] += p.clone() * transition.p.clone();
+            }
+            return;
+        }
+    }
+
+    let mut remaining: FnvHashSet<usize> = component.iter().copied().collect();
+    let mut rows: FnvHashMap<usize, FnvHashMap<usize, TProb>> = component
+        .iter()
+        .map(|state| {
+            let row = transitions[*state]
+                .iter()
+                .map(|t| (t.item, t.p.clone()))
+                .collect();
+            (*state, row)
+        })
+        .collect();
+    let mut exits: FnvHashMap<usize, TProb> = FnvHashMap::default();
+
+    for state in component {
+        remaining.remove(state);
+        let mut row = rows.remove(state).unwrap();
+        row.remove(state);
+        let leaving: TProb = row.values().cloned().sum();
+        if leaving == TProb::zero() {
+            stalemated.push(*state);
+            continue;
+        }
+
+        let row: Vec<(usize, TProb)> = row
+            .into_iter()
+            .map(|(next, p)| (next, p / leaving.clone()))
+            .collect();
+        let p = std::mem::take(&mut mass[*state]);
+        for (next, next_p) in row.iter() {
+            let flow = p.clone() * next_p.clone();
+            if remaining.contains(next) {
+                mass[*next] += flow;
+            } else {
+                *exits.entry(*next).or_default() += flow;
+            }
+        }
+        for other in remaining.iter() {
+            let other_row = rows.get_mut(other).unwrap();
+            if let Some(into_p) = other_row.remove(state) {
+                for (next, next_p) in row.iter() {
+                    *other_row.entry(*next).or_default() += into_p.clone() * next_p.clone();
+                }
+            }
+        }
+    }
+
+    for (state, p) in exits {
+        mass[state] += p;
+    }
+}
+
+/// Finds the strongly connected components of the transition graph using Tarjan's algorithm.
+/// Components are returned in reverse topological order.
+fn strongly_connected_components<TProb>(
+    transitions: &[Vec<Prob<usize, TProb>>],
+) -> Vec<Vec<usize>> {
+    const UNVISITED: usize = usize::MAX;
+    let count = transitions.len();
+    let mut indices = vec![UNVISITED; count];
+    let mut low_links = vec![0; count];
+    let mut on_stack = vec![false; count];
+    let mut stack = Vec::new();
+    let mut components = Vec::new();
+    let mut next_index = 0;
+
+    for root in 0..count {
+        if indices[root] != UNVISITED {
+            continue;
+        }
+
+        // Each frame holds a state and the index of the next transition to visit.
+        let mut frames = vec![(root, 0)];
+        indices[root] = next_index;
+        low_links[root] = next_index;
+        next_index += 1;
+        stack.push(root);
+        on_stack[root] = true;
+
+        while let Some(&(state, edge)) = frames.last() {
+            if let Some(transition) = transitions[state].get(edge) {
+                frames.last_mut().unwrap().1 += 1;
+                let next = transition.item;
+                if indices[next] == UNVISITED {
+                    indices[next] = next_index;
+                    low_links[next] = next_index;
+                    next_index += 1;
+                    stack.push(next);
+                    on_stack[next] = true;
+                    frames.push((next, 0));
+                } else if on_stack[next] {
+                    low_links[state] = low_links[state].min(indices[next]);
+                }
+                continue;
+            }
+
+            frames.pop();
+            if let Some(&(parent, _)) = frames.last() {
+                low_links[parent] = low_links[parent].min(low_links[state]);
+            }
+            if low_links[state] == indices[state] {
+                let mut component = Vec::new();
+                loop {
+                    let member = stack.pop().unwrap();
+                    on_stack[member] = false;
+                    component.push(member);
+                    if member == state {
+                        break;
+                    }
+                }
+                components.push(component);
+            }
+        }
+    }
+    components
+}
+
+/// The exact outcome of a battle, as computed by a `MarkovSolver`.
+#[derive(Debug)]
+pub struct MarkovSolution<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue = Probability> {
+    /// Every combat the battle can end in, and its probability.
+    pub completed: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    /// Every combat that can never complete, and the probability of reaching it.
+    pub stalemated: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    /// The number of distinct states the battle can be in.
+    pub state_count: usize,
+    prebattle: RoundResult<TBattlePhase, TUnit, TProb>,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    MarkovSolution<TBattlePhase, TUnit, TProb>
+{
+    /// The probability that the battle never completes.
+    pub fn stalemate_p(&self) -> TProb {
+        self.stalemated.outcomes().iter().map(|o| o.p.clone()).sum()
+    }
+
+    /// Summarizes the solution.
+    ///
+    /// Since the solution doesn't distinguish between rounds, the entire battle is summarized as
+    /// a single round following the prebattle round. Nor does it track the phase in which units
+    /// were lost, so the summary has no casualties.
+    pub fn summarize(&self) -> BattleSummary<TBattlePhase, TUnit, TProb> {
+        let mut surviving_attackers = ProbDistBuilder::new();
+        let mut surviving_defenders = ProbDistBuilder::new();
+        let mut total_probability = TProb::zero();
+        for combat in self
+            .completed
+            .outcomes()
+            .iter()
+            .chain(self.stalemated.outcomes())
+        {
+            surviving_attackers.add(combat.item.attackers.clone(), combat.p.clone());
+            surviving_defenders.add(combat.item.defenders.clone(), combat.p.clone());
+            total_probability += combat.p.clone();
+        }
+
+        let round = RoundResult {
+            index: 1,
+            completed: self.completed.clone(),
+            stalemated: self.stalemated.clone(),
+            surviving_attackers: surviving_attackers.build(),
+            surviving_defenders: surviving_defenders.build(),
+            total_probability,
+            stalemate: !self.stalemated.is_empty(),
+            ..Default::default()
+        };
+
+        let mut summarizer = Summarizer::new(&self.prebattle);
+        summarizer.add_round(&round);
+        summarizer.summarize()
+    }
+}
@@ -1,4 +1,4 @@
-use crate::Probability;
+use crate::{ProbValue, Probability};
 use core::{hash::Hash, ops::Mul};
 use fnv::FnvBuildHasher;
 use std::collections::HashMap;
@@ -6,25 +6,25 @@ use std::collections::HashMap;
 /// An item that has an associated `Probabilty` of occurrance.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
-pub struct Prob<T> {
+pub struct Prob<T, TProb = Probability> {
     /// The inner item.
     pub item: T,
     /// The probability of occurrance.
-    pub p: Probability,
+    pub p: TProb,
 }
 
-impl<T> Prob<T> {
+impl<T, TProb> Prob<T, TProb> {
     /// Constructs a new `Prob` with item `item` and a probabilty of
     /// `Probability`.
-    pub fn new(item: T, p: Probability) -> Prob<T> {
+    pub fn new(item: T, p: TProb) -> Prob<T, TProb> {
         Prob { item, p }
     }
 }
 
-impl<T> Mul<Probability> for Prob<T> {
-    type Output = Prob<T>;
+impl<T, TProb: ProbValue> Mul<TProb> for Prob<T, TProb> {
+    type Output = Prob<T, TProb>;
 
-    fn mul(self, rhs: Probability) -> Self::Output {
+    fn mul(self, rhs: TProb) -> Self::Output {
         Prob {
             item: self.item,
             p: self.p * rhs,
@@ -35,16 +35,27 @@ impl<T> Mul<Probability> for Prob<T> {
 /// A discrete probability distribution of `T`.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
-pub struct ProbDist<T> {
-    outcomes: Vec<Prob<T>>,
+pub struct ProbDist<T, TProb = Probability> {
+    outcomes: Vec<Prob<T, TProb>>,
 }
 
-impl<T> ProbDist<T> {
+impl<T, TProb> ProbDist<T, TProb> {
+    /// Constructs a distribution from outcomes which are already known to be distinct, keeping
+    /// them in order.
+    pub(crate) fn from_distinct(outcomes: Vec<Prob<T, TProb>>) -> Self {
+        Self { outcomes }
+    }
+
     /// Returns a slice of `Prob<T>` representing this probability distribution.
-    pub fn outcomes(&self) -> &[Prob<T>] {
+    pub fn outcomes(&self) -> &[Prob<T, TProb>] {
         &self.outcomes
     }
 
+    /// Consumes this distribution and returns the `Prob<T>`s that it's composed of.
+    pub fn into_outcomes(self) -> Vec<Prob<T, TProb>> {
+        self.outcomes
+    }
+
     /// The number of discrete items in this distribution.
     pub fn len(&self) -> usize {
         self.outcomes.len()
@@ -56,8 +67,91 @@ impl<T> ProbDist<T> {
     }
 }
 
-impl<T: Eq + Hash> From<Vec<Prob<T>>> for ProbDist<T> {
-    fn from(outcomes: Vec<Prob<T>>) -> Self {
+impl<T, TProb: ProbValue> ProbDist<T, TProb> {
+    /// The total probability of every item in this distribution.
+    pub fn total_p(&self) -> TProb {
+        self.outcomes.iter().map(|o| o.p.clone()).sum()
+    }
+}
+
+impl<T: Clone, TProb: ProbValue> ProbDist<T, TProb> {
+    /// Constructs a copy of this distribution with the probability of each item divided by
+    /// `divisor`, keeping the items in the same order.
+    pub fn divide(&self, divisor: &TProb) -> Self {
+        ProbDist {
+            outcomes: self
+                .outcomes
+                .iter()
+                .map(|o| Prob::new(o.item.clone(), o.p.clone() / divisor.clone()))
+                .collect(),
+        }
+    }
+}
+
+impl<T: Ord, TProb: ProbValue> ProbDist<T, TProb> {
+    /// Sorts the items of this distribution in ascending order.
+    pub fn sort(&mut self) {
+        self.outcomes.sort_by(|a, b| a.item.cmp(&b.item));
+    }
+
+    /// The probability of an item at or below `item`, relative to the total probability of this
+    /// distribution.
+    pub fn cdf(&self, item: &T) -> TProb {
+        let total_p = self.total_p();
+        if total_p == TProb::zero() {
+            return total_p;
+        }
+        let p: TProb = self
+            .outcomes
+            .iter()
+            .filter(|o| o.item <= *item)
+            .map(|o| o.p.clone())
+            .sum();
+        p / total_p
+    }
+
+    /// The probability of an item strictly below `item`, relative to the total probability of
+    /// this distribution.
+    pub fn cdf_below(&self, item: &T) -> TProb {
+        let total_p = self.total_p();
+        if total_p == TProb::zero() {
+            return total_p;
+        }
+        let p: TProb = self
+            .outcomes
+            .iter()
+            .filter(|o| o.item < *item)
+            .map(|o| o.p.clone())
+            .sum();
+        p / total_p
+    }
+
+    /// The smallest item where at least `q` of the total probability of this distribution lies
+    /// at or below it, or `None` if this distribution is empty.
+    pub fn quantile(&self, q: &TProb) -> Option<&T> {
+        let mut sorted: Vec<_> = self.outcomes.iter().collect();
+        sorted.sort_by(|a, b| a.item.cmp(&b.item));
+        let target = q.clone() * self.total_p();
+        let mut cumulative = TProb::zero();
+        for outcome in sorted.iter() {
+            cumulative += outcome.p.clone();
+            if cumulative >= target {
+                return Some(&outcome.item);
+            }
+        }
+        // Rounding may leave the cumulative probability just short of the total.
+        sorted.last().map(|o| &o.item)
+    }
+
+    /// The item which splits the probability of this distribution in half, or `None` if this
+    /// distribution is empty.
+    pub fn median(&self) -> Option<&T> {
+        self.quantile(&TProb::from_ratio(1, 2))
+    }
+}
+
+impl<T: Eq + Hash, TProb: ProbValue> From<Vec<Prob<T, TProb>>> for ProbDist<T, TProb> {
+    fn from(outcomes: Vec<Prob<T, TProb>>) -> Self {
         let mut builder = ProbDistBuilder::with_capacity(outcomes.len());
         for outcome in outcomes.into_iter() {
             builder.add_prob(outcome);
@@ -66,21 +160,21 @@ impl<T: Eq + Hash> From<Vec<Prob<T>>> for ProbDist<T> {
     }
 }
 
-impl<T> Default for ProbDist<T> {
+impl<T, TProb> Default for ProbDist<T, TProb> {
     fn default() -> Self {
         Self {
-            outcomes: Vec::<Prob<T>>::new(),
+            outcomes: Vec::<Prob<T, TProb>>::new(),
         }
     }
 }
 
 /// A builder to facilitate piecemeal construction of a `ProbDist`.
 #[derive(Debug, Clone, PartialEq)]
-pub struct ProbDistBuilder<T: Eq + Hash> {
-    outcomes: HashMap<T, Probability, FnvBuildHasher>,
+pub struct ProbDistBuilder<T: Eq + Hash, TProb = Probability> {
+    outcomes: HashMap<T, TProb, FnvBuildHasher>,
 }
 
-impl<T: Eq + Hash> ProbDistBuilder<T> {
+impl<T: Eq + Hash, TProb: ProbValue> ProbDistBuilder<T, TProb> {
     /// Constructs a new `ProbDistBuilder`.
     pub fn new() -> Self {
         Self {
@@ -96,7 +190,7 @@ impl<T: Eq + Hash> ProbDistBuilder<T> {
     }
 
     /// Consumes this builder and returns a `ProbDist`.
-    pub fn build(self) -> ProbDist<T> {
+    pub fn build(self) -> ProbDist<T, TProb> {
         ProbDist {
             outcomes: self
                 .outcomes
@@ -117,13 +211,13 @@ impl<T: Eq + Hash> ProbDistBuilder<T> {
     }
 
     /// Adds `item` to this distrbution with a probability of `p`.
-    pub fn add(&mut self, item: T, p: Probability) {
+    pub fn add(&mut self, item: T, p: TProb) {
         self.add_prob(Prob::new(item, p));
     }
 
     /// Adds the item in `prob` to this distrbution with the associated `Probability`.
-    pub fn add_prob(&mut self, prob: Prob<T>) {
-        if prob.p == Probability::zero() {
+    pub fn add_prob(&mut self, prob: Prob<T, TProb>) {
+        if prob.p == TProb::zero() {
             return;
         }
         match self.outcomes.entry(prob.item) {
@@ -135,30 +229,63 @@ impl<T: Eq + Hash> ProbDistBuilder<T> {
             }
         }
     }
+
+    /// Adds every item in `other` to this distribution, consuming `other`.
+    pub fn merge(&mut self, other: ProbDistBuilder<T, TProb>) {
+        self.outcomes.reserve(other.len());
+        for (item, p) in other.outcomes.into_iter() {
+            self.add(item, p);
+        }
+    }
 }
 
-impl<T: Clone + Eq + Hash> ProbDistBuilder<T> {
+impl<T: Clone + Eq + Hash, TProb: ProbValue> ProbDistBuilder<T, TProb> {
     /// Clones each item in this builder and returns a `ProbDist` with the
     /// clones.
-    pub fn build_cloned(&self) -> ProbDist<T> {
+    pub fn build_cloned(&self) -> ProbDist<T, TProb> {
         ProbDist {
             outcomes: self
                 .outcomes
                 .iter()
-                .map(|t| Prob::new(t.0.clone(), *t.1))
+                .map(|t| Prob::new(t.0.clone(), t.1.clone()))
                 .collect(),
         }
     }
 }
 
-impl<T: Eq + Hash> Default for ProbDistBuilder<T> {
+/// Serialized as a sequence of item and probability pairs, since many formats only support maps
+/// with string keys.
+#[cfg(feature = "serde1")]
+impl<T: Eq + Hash + serde::Serialize, TProb: serde::Serialize> serde::Serialize
+    for ProbDistBuilder<T, TProb>
+{
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.outcomes.iter())
+    }
+}
+
+#[cfg(feature = "serde1")]
+impl<'de, T, TProb> serde::Deserialize<'de> for ProbDistBuilder<T, TProb>
+where
+    T: Eq + Hash + serde::Deserialize<'de>,
+    TProb: serde::Deserialize<'de>,
+{
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let outcomes = Vec::<(T, TProb)>::deserialize(deserializer)?;
+        Ok(Self {
+            outcomes: outcomes.into_iter().collect(),
+        })
+    }
+}
+
+impl<T: Eq + Hash, TProb: ProbValue> Default for ProbDistBuilder<T, TProb> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<T: Eq + Hash> From<ProbDist<T>> for ProbDistBuilder<T> {
-    fn from(dist: ProbDist<T>) -> Self {
+impl<T: Eq + Hash, TProb: ProbValue> From<ProbDist<T, TProb>> for ProbDistBuilder<T, TProb> {
+    fn from(dist: ProbDist<T, TProb>) -> Self {
         let mut outcomes = HashMap::with_capacity_and_hasher(dist.len(), Default::default());
         for outcome in dist.outcomes.into_iter() {
             outcomes.insert(outcome.item, outcome.p);
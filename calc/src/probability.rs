@@ -1,9 +1,50 @@
 use core::convert::TryFrom;
-use core::fmt::Display;
+use core::fmt::{Debug, Display};
 use core::iter::{Iterator, Sum};
-use core::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
+use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Sub, SubAssign};
+
+/// A representation of the probability of an event occurring, always lying within [0, 1].
+///
+/// The calculation engine is generic over the representation. `Probability` is the default
+/// floating point representation, while `ExactProbability` (behind the `exact` feature)
+/// represents probabilities as arbitrary precision rationals.
+pub trait ProbValue:
+    Debug
+    + Display
+    + Clone
+    + PartialEq
+    + PartialOrd
+    + Default
+    + Sum
+    + Add<Output = Self>
+    + AddAssign
+    + Sub<Output = Self>
+    + SubAssign
+    + Mul<Output = Self>
+    + MulAssign
+    + Div<Output = Self>
+    + 'static
+{
+    /// Returns a probability of `0`.
+    fn zero() -> Self;
+
+    /// Returns a probability of `1`.
+    fn one() -> Self;
+
+    /// Returns a probability of `numer` / `denom`.
+    ///
+    /// Panics if `numer` is greater than `denom` or if `denom` is equal to 0.
+    fn from_ratio(numer: u32, denom: u32) -> Self;
+
+    /// Returns the probability of each number of successes, from 0 to `trials` inclusive, when
+    /// each trial succeeds with a probability of `numer` / `denom`.
+    fn binomial(numer: u32, denom: u32, trials: u32) -> Vec<Self>;
+
+    /// Converts this probability to the nearest `f64`.
+    fn to_f64(&self) -> f64;
+}
 
-/// The probability of an event occurring. Guarnteed to lie within [0, 1].
+/// The probability of an event occurring. Guaranteed to lie within [0, 1].
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub struct Probability {
@@ -69,7 +110,7 @@ impl Default for Probability {
 impl TryFrom<f64> for Probability {
     type Error = &'static str;
     fn try_from(value: f64) -> Result<Self, Self::Error> {
-        if value < 0.0 || value > 1.0 {
+        if !(0.0..=1.0).contains(&value) {
             Err("Probability value must be between 0 and 1.")
         } else {
             Ok(Self { value })
@@ -147,9 +188,29 @@ impl MulAssign for Probability {
     }
 }
 
+impl Div<Probability> for Probability {
+    type Output = Self;
+
+    fn div(self, rhs: Probability) -> Self::Output {
+        if rhs.value == 0.0 {
+            panic!("Division by a probability of 0.");
+        }
+        let value = self.value / rhs.value;
+        if value > 1.0 + TOLERANCE {
+            panic!(
+                "Div results in a probability greater than 1: {} / {}",
+                self.value, rhs.value
+            );
+        }
+        Self {
+            value: if value > 1.0 { 1.0 } else { value },
+        }
+    }
+}
+
 impl Display for Probability {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
-        self.value.fmt(f)
+        Display::fmt(&self.value, f)
     }
 }
 
@@ -158,3 +219,32 @@ impl From<Probability> for f64 {
         value.value
     }
 }
+
+impl ProbValue for Probability {
+    fn zero() -> Self {
+        Probability::zero()
+    }
+
+    fn one() -> Self {
+        Probability::one()
+    }
+
+    fn from_ratio(numer: u32, denom: u32) -> Self {
+        Probability::from_ratio(numer, denom)
+    }
+
+    fn binomial(numer: u32, denom: u32, trials: u32) -> Vec<Self> {
+        use core::convert::TryInto;
+        use statrs::distribution::{Binomial, Discrete};
+
+        let p = numer as f64 / denom as f64;
+        let binomial = Binomial::new(p, trials as u64).unwrap();
+        (0..=trials)
+            .map(|successes| binomial.pmf(successes as u64).try_into().unwrap())
+            .collect()
+    }
+
+    fn to_f64(&self) -> f64 {
+        self.value
+    }
+}
@@ -1,19 +1,83 @@
-use crate::{Prob, Probability};
+use crate::{Prob, ProbValue, Probability};
+use std::time::{Duration, Instant};
+
+/// Decides which outcomes of a round are too unlikely to be worth tracking.
+///
+/// Pruning happens in two passes. While a round is computed, each outcome at or below the
+/// threshold of the strategy's `Pruner` is discarded as soon as it's produced. Once the round is
+/// complete, the strategy may discard any number of the least likely pending combats as well.
+pub trait PruningStrategy<TProb: ProbValue = Probability> {
+    /// Returns the `Pruner` used while computing the next round.
+    fn pruner(&mut self) -> Pruner<TProb>;
+
+    /// Returns how many of the least likely pending combats to prune at the end of a round,
+    /// given the probability of each pending combat in ascending order.
+    fn prune_count(&mut self, pending: &[TProb]) -> usize;
+
+    /// Returns the settings of this strategy, so that it can be saved in a checkpoint, or None
+    /// if it can't be saved.
+    fn settings(&self) -> Option<PruningSettings<TProb>> {
+        None
+    }
+}
+
+/// The settings of one of the built-in pruning strategies, from which the strategy can be
+/// recreated.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub enum PruningSettings<TProb = Probability> {
+    /// A `Pruner` with the given threshold.
+    Threshold(TProb),
+    /// A `TopK` keeping the given number of pending combats.
+    TopK(usize),
+    /// A `MassBudget` with the given budget.
+    MassBudget(TProb),
+    /// An `AdaptiveThreshold`, including its current threshold.
+    Adaptive {
+        minimum: TProb,
+        threshold: TProb,
+        max_pending: usize,
+        max_duration: Option<Duration>,
+    },
+}
+
+impl<TProb: ProbValue> PruningSettings<TProb> {
+    /// Recreates the strategy these settings were taken from.
+    pub fn into_strategy(self) -> Box<dyn PruningStrategy<TProb>> {
+        match self {
+            PruningSettings::Threshold(threshold) => Box::new(Pruner::new(threshold)),
+            PruningSettings::TopK(k) => Box::new(TopK { k }),
+            PruningSettings::MassBudget(budget) => Box::new(MassBudget { budget }),
+            PruningSettings::Adaptive {
+                minimum,
+                threshold,
+                max_pending,
+                max_duration,
+            } => Box::new(AdaptiveThreshold {
+                minimum,
+                threshold,
+                max_pending,
+                max_duration,
+                round_start: None,
+            }),
+        }
+    }
+}
 
 /// Prunes outcomes with a probability at or below the threshold.
 #[derive(Debug, Clone, Copy)]
-pub struct Pruner {
+pub struct Pruner<TProb = Probability> {
     /// The threshold at which outcomes are pruned.
-    pub threshold: Probability,
+    pub threshold: TProb,
     /// The number of outcomes that were pruned.
     pub count: usize,
     /// The cumulative probability of outcomes pruned.
-    pub sum: Probability,
+    pub sum: TProb,
 }
 
-impl Pruner {
+impl<TProb: ProbValue> Pruner<TProb> {
     /// Constructs a new `Pruner` with the given pruning threshold.
-    pub fn new(threshold: Probability) -> Pruner {
+    pub fn new(threshold: TProb) -> Pruner<TProb> {
         Pruner {
             threshold,
             count: 0,
@@ -24,31 +88,204 @@ impl Pruner {
     /// Returns whether or not the given `Prob` will be pruned.
     ///
     /// `probable` will be considered prunable if `probable.p <= self.threshold`.
-    pub fn check<T>(&self, probable: &Prob<T>) -> bool {
+    pub fn check<T>(&self, probable: &Prob<T, TProb>) -> bool {
         probable.p <= self.threshold
     }
 
     /// Returns whether or not the given `Prob` will be pruned and accumulates the probability and count.
     ///
     /// `probable` will be considered pruned if `probable.p <= self.threshold`.
-    pub fn prune<T>(&mut self, probable: &Prob<T>) -> bool {
+    pub fn prune<T>(&mut self, probable: &Prob<T, TProb>) -> bool {
         if !self.check(probable) {
             return false;
         }
 
         self.count += 1;
-        self.sum += probable.p;
+        self.sum += probable.p.clone();
         true
     }
+
+    /// Adds the count and cumulative probability pruned by `other` to this pruner.
+    pub fn merge(&mut self, other: &Pruner<TProb>) {
+        self.count += other.count;
+        self.sum += other.sum.clone();
+    }
 }
 
-impl Default for Pruner {
+impl<TProb: ProbValue, S: PruningStrategy<TProb> + ?Sized> PruningStrategy<TProb> for Box<S> {
+    fn pruner(&mut self) -> Pruner<TProb> {
+        (**self).pruner()
+    }
+
+    fn prune_count(&mut self, pending: &[TProb]) -> usize {
+        (**self).prune_count(pending)
+    }
+
+    fn settings(&self) -> Option<PruningSettings<TProb>> {
+        (**self).settings()
+    }
+}
+
+/// Prunes each outcome with a probability at or below the threshold.
+impl<TProb: ProbValue> PruningStrategy<TProb> for Pruner<TProb> {
+    fn pruner(&mut self) -> Pruner<TProb> {
+        Pruner::new(self.threshold.clone())
+    }
+
+    fn prune_count(&mut self, _: &[TProb]) -> usize {
+        0
+    }
+
+    fn settings(&self) -> Option<PruningSettings<TProb>> {
+        Some(PruningSettings::Threshold(self.threshold.clone()))
+    }
+}
+
+impl<TProb: ProbValue> Default for Pruner<TProb> {
     fn default() -> Self {
-        use std::convert::TryInto;
         Self {
-            threshold: 0.000000001.try_into().unwrap(),
+            threshold: TProb::from_ratio(1, 1_000_000_000),
             count: 0,
             sum: Default::default(),
         }
     }
 }
+
+/// Keeps only the `k` most likely pending combats at the end of each round.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TopK {
+    /// The maximum number of pending combats to keep.
+    pub k: usize,
+}
+
+impl<TProb: ProbValue> PruningStrategy<TProb> for TopK {
+    fn pruner(&mut self) -> Pruner<TProb> {
+        Pruner::new(TProb::zero())
+    }
+
+    fn prune_count(&mut self, pending: &[TProb]) -> usize {
+        pending.len().saturating_sub(self.k)
+    }
+
+    fn settings(&self) -> Option<PruningSettings<TProb>> {
+        Some(PruningSettings::TopK(self.k))
+    }
+}
+
+/// Prunes the least likely pending combats at the end of each round, for as long as their
+/// cumulative probability stays within the budget.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MassBudget<TProb = Probability> {
+    /// The total probability which may be pruned each round.
+    pub budget: TProb,
+}
+
+impl<TProb: ProbValue> PruningStrategy<TProb> for MassBudget<TProb> {
+    fn pruner(&mut self) -> Pruner<TProb> {
+        Pruner::new(TProb::zero())
+    }
+
+    fn prune_count(&mut self, pending: &[TProb]) -> usize {
+        let mut sum = TProb::zero();
+        pending
+            .iter()
+            .take_while(|p| {
+                sum += (*p).clone();
+                sum <= self.budget
+            })
+            .count()
+    }
+
+    fn settings(&self) -> Option<PruningSettings<TProb>> {
+        Some(PruningSettings::MassBudget(self.budget.clone()))
+    }
+}
+
+/// A pruning threshold that adapts to the size of the battle.
+///
+/// Whenever a round leaves more pending combats than allowed, or takes longer to compute than
+/// allowed, the threshold is raised tenfold and any pending combats at or below it are pruned.
+/// Once a round leaves fewer than a quarter of the allowed pending combats, the threshold is
+/// lowered tenfold again, but never below the initial threshold.
+#[derive(Debug, Clone)]
+pub struct AdaptiveThreshold<TProb = Probability> {
+    minimum: TProb,
+    threshold: TProb,
+    max_pending: usize,
+    max_duration: Option<Duration>,
+    round_start: Option<Instant>,
+}
+
+impl<TProb: ProbValue> AdaptiveThreshold<TProb> {
+    /// Constructs a new `AdaptiveThreshold`, starting at `threshold`, which tries to keep the
+    /// number of pending combats at or below `max_pending`.
+    pub fn new(threshold: TProb, max_pending: usize) -> Self {
+        AdaptiveThreshold {
+            minimum: threshold.clone(),
+            threshold,
+            max_pending,
+            max_duration: None,
+            round_start: None,
+        }
+    }
+
+    /// Additionally tries to keep the time taken to compute each round at or below
+    /// `max_duration`.
+    ///
+    /// Time can't be measured on `wasm32-unknown-unknown`, so this must not be used there.
+    pub fn with_max_duration(mut self, max_duration: Duration) -> Self {
+        self.max_duration = Some(max_duration);
+        self
+    }
+
+    /// Gets the current threshold.
+    pub fn threshold(&self) -> TProb {
+        self.threshold.clone()
+    }
+
+    fn over_time(&self) -> bool {
+        match (self.max_duration, self.round_start) {
+            (Some(max_duration), Some(round_start)) => round_start.elapsed() > max_duration,
+            _ => false,
+        }
+    }
+}
+
+impl<TProb: ProbValue> PruningStrategy<TProb> for AdaptiveThreshold<TProb> {
+    fn pruner(&mut self) -> Pruner<TProb> {
+        if self.max_duration.is_some() {
+            self.round_start = Some(Instant::now());
+        }
+        Pruner::new(self.threshold.clone())
+    }
+
+    fn prune_count(&mut self, pending: &[TProb]) -> usize {
+        // Raising a threshold of zero starts from the smallest step instead.
+        let step = TProb::from_ratio(1, 1_000_000_000);
+        let factor = TProb::from_ratio(1, 10);
+        if pending.len() > self.max_pending || self.over_time() {
+            if self.threshold < step {
+                self.threshold = step;
+            } else if self.threshold <= factor {
+                self.threshold = self.threshold.clone() / factor;
+            }
+        } else if pending.len() < self.max_pending / 4 && self.threshold > self.minimum {
+            let lowered = self.threshold.clone() * factor;
+            self.threshold = if lowered < self.minimum || lowered < step {
+                self.minimum.clone()
+            } else {
+                lowered
+            };
+        }
+        pending.iter().take_while(|p| **p <= self.threshold).count()
+    }
+
+    fn settings(&self) -> Option<PruningSettings<TProb>> {
+        Some(PruningSettings::Adaptive {
+            minimum: self.minimum.clone(),
+            threshold: self.threshold.clone(),
+            max_pending: self.max_pending,
+            max_duration: self.max_duration,
+        })
+    }
+}
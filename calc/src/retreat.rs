@@ -0,0 +1,69 @@
+use crate::*;
+
+/// Decides whether or not the attackers retreat from a pending combat.
+///
+/// Retreats are only considered at the end of a full combat round, i.e. after the last phase
+/// in the `cycle` of the `PhaseSequence` has occurred.
+pub trait RetreatPolicy<TBattlePhase, TUnit>
+where
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+{
+    /// Returns whether or not the attackers retreat from `combat` after `rounds_completed`
+    /// full combat rounds.
+    fn should_retreat(&self, combat: &Combat<TBattlePhase, TUnit>, rounds_completed: usize)
+        -> bool;
+}
+
+/// Retreats once the given number of full combat rounds have been fought.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetreatAfterRounds {
+    /// The number of full combat rounds to fight before retreating.
+    pub rounds: usize,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit> RetreatPolicy<TBattlePhase, TUnit>
+    for RetreatAfterRounds
+{
+    fn should_retreat(&self, _: &Combat<TBattlePhase, TUnit>, rounds_completed: usize) -> bool {
+        rounds_completed >= self.rounds
+    }
+}
+
+/// Retreats once the remaining attackers are worth less than the given amount of IPC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetreatBelowIpc {
+    /// The IPC value below which the attackers retreat.
+    pub ipc: u32,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit> RetreatPolicy<TBattlePhase, TUnit>
+    for RetreatBelowIpc
+{
+    fn should_retreat(&self, combat: &Combat<TBattlePhase, TUnit>, _: usize) -> bool {
+        let ipc: u32 = combat
+            .attackers
+            .outcomes()
+            .iter()
+            .map(|q| q.item.ipc() * q.count)
+            .sum();
+        ipc < self.ipc
+    }
+}
+
+/// Any predicate on a `Combat` and the number of full combat rounds completed can be used as a
+/// retreat policy.
+impl<TBattlePhase, TUnit, F> RetreatPolicy<TBattlePhase, TUnit> for F
+where
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    F: Fn(&Combat<TBattlePhase, TUnit>, usize) -> bool,
+{
+    fn should_retreat(
+        &self,
+        combat: &Combat<TBattlePhase, TUnit>,
+        rounds_completed: usize,
+    ) -> bool {
+        self(combat, rounds_completed)
+    }
+}
@@ -5,22 +5,69 @@ use std::marker::PhantomData;
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Hash, Copy, Clone)]
 pub struct Roll<TUnit: Unit, THit: Hit<TUnit>> {
-    /// The likeliness of this roll to succeed.
+    /// The likeliness of this roll to succeed. The roll succeeds if the die shows a value less
+    /// than or equal to the strength, so a strength at or above the number of sides always hits.
     pub strength: u8,
+    /// The number of sides of the die being rolled.
+    pub sides: u8,
     /// The hit that results if this roll succeeds.
     pub hit: THit,
     phantom_unit: PhantomData<TUnit>,
 }
 
 impl<TUnit: Unit, THit: Hit<TUnit>> Roll<TUnit, THit> {
-    /// Constructs a new `Roll` with the given strength and hit.
+    /// The number of sides of the die used when none is specified.
+    pub const DEFAULT_SIDES: u8 = 6;
+
+    /// Constructs a new `Roll` of a six-sided die with the given strength and hit.
     pub fn new(strength: u8, hit: THit) -> Self {
+        Self::with_sides(strength, Self::DEFAULT_SIDES, hit)
+    }
+
+    /// Constructs a new `Roll` of a die with `sides` sides, with the given strength and hit.
+    ///
+    /// Panics if `sides` is zero.
+    pub fn with_sides(strength: u8, sides: u8, hit: THit) -> Self {
+        if sides == 0 {
+            panic!("A die must have at least one side.");
+        }
         Roll {
             strength,
+            sides,
             hit,
             phantom_unit: PhantomData,
         }
     }
+
+    /// The number of faces of the die on which this roll succeeds.
+    pub fn successes(&self) -> u8 {
+        core::cmp::min(self.strength, self.sides)
+    }
+}
+
+/// The rules used to turn rolls into hits.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Hash, Copy, Clone, Default)]
+pub enum DiceMode {
+    /// Each roll is made on its own die.
+    #[default]
+    Standard,
+    /// The strength of every roll of the same hit is totaled; each full die's worth of strength
+    /// is a guaranteed hit, and only the remainder is rolled on a single die.
+    LowLuck,
+}
+
+/// The order in which the two sides fire during a phase.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Hash, Copy, Clone, Default)]
+pub enum FiringOrder {
+    /// Both sides fire at once, so casualties fire back before they're removed.
+    #[default]
+    Simultaneous,
+    /// The attackers fire and remove their casualties before the defenders fire back.
+    AttackerFirst,
+    /// The defenders fire and remove their casualties before the attackers fire back.
+    DefenderFirst,
 }
 
 /// A type that selects rolls according to the combat context.
@@ -35,4 +82,16 @@ where
         &self,
         context: &CombatContext<TBattlePhase, TUnit>,
     ) -> QuantDist<Roll<TUnit, THit>>;
+
+    /// Selects the dice mode used to resolve the rolls for the combat context. Defaults to
+    /// `DiceMode::Standard`.
+    fn dice_mode(&self, _context: &CombatContext<TBattlePhase, TUnit>) -> DiceMode {
+        DiceMode::Standard
+    }
+
+    /// Selects the order in which the sides fire in the combat. Defaults to
+    /// `FiringOrder::Simultaneous`.
+    fn firing_order(&self, _combat: &Combat<TBattlePhase, TUnit>) -> FiringOrder {
+        FiringOrder::Simultaneous
+    }
 }
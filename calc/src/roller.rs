@@ -1,42 +1,59 @@
 use crate::*;
-use statrs::distribution::{Binomial, Discrete};
 use std::collections::hash_map::*;
+use std::collections::BTreeMap;
+use std::hash::Hash;
+use std::sync::{Arc, RwLock};
+
+type HitCache<TUnit, THit, TProb> =
+    HashMap<(QuantDist<Roll<TUnit, THit>>, DiceMode), Arc<ProbDist<QuantDist<THit>, TProb>>>;
 
 /// Computes and caches rolls given a distribution of rolls.
-pub struct Roller<TUnit: Unit, THit: Hit<TUnit>> {
-    cache: HashMap<QuantDist<Roll<TUnit, THit>>, ProbDist<QuantDist<THit>>>,
+///
+/// The cache is guarded by a lock, so a single `Roller` can be shared between threads.
+pub struct Roller<TUnit: Unit, THit: Hit<TUnit>, TProb: ProbValue = Probability> {
+    cache: RwLock<HitCache<TUnit, THit, TProb>>,
 }
 
-impl<TUnit: Unit, THit: Hit<TUnit>> Roller<TUnit, THit> {
-    /// Expands the rolls into a probability distribution of all the hits that could occur and caches them.
+impl<TUnit: Unit, THit: Hit<TUnit>, TProb: ProbValue> Roller<TUnit, THit, TProb> {
+    /// Expands the rolls into a probability distribution of all the hits that could occur under
+    /// the given dice mode and caches them.
     pub fn roll_hits(
-        &mut self,
+        &self,
         strike: QuantDist<Roll<TUnit, THit>>,
-    ) -> &ProbDist<QuantDist<THit>> {
-        match self.cache.entry(strike) {
-            Entry::Vacant(vacant) => {
-                let value = roll_hits(vacant.key());
-                vacant.insert(value)
-            }
-            Entry::Occupied(occupied) => occupied.into_mut(),
+        mode: DiceMode,
+    ) -> Arc<ProbDist<QuantDist<THit>, TProb>> {
+        let key = (strike, mode);
+        if let Some(hits) = self.cache.read().unwrap().get(&key) {
+            return hits.clone();
         }
+
+        // Expand the rolls without holding the lock; if another thread beat us to it, we use
+        // whichever result made it into the cache first.
+        let hits = Arc::new(match mode {
+            DiceMode::Standard => roll_hits(&key.0),
+            DiceMode::LowLuck => roll_hits_low_luck(&key.0),
+        });
+        self.cache
+            .write()
+            .unwrap()
+            .entry(key)
+            .or_insert(hits)
+            .clone()
     }
 }
 
-impl<TUnit: Unit, THit: Hit<TUnit>> Default for Roller<TUnit, THit> {
+impl<TUnit: Unit, THit: Hit<TUnit>, TProb: ProbValue> Default for Roller<TUnit, THit, TProb> {
     fn default() -> Self {
         Self {
-            cache: HashMap::new(),
+            cache: RwLock::new(HashMap::new()),
         }
     }
 }
 
 /// Expands the rolls into a probability distribution of all the hits that could occur.
-pub fn roll_hits<TUnit: Unit, THit: Hit<TUnit>>(
+pub fn roll_hits<TUnit: Unit, THit: Hit<TUnit>, TProb: ProbValue>(
     strike: &QuantDist<Roll<TUnit, THit>>,
-) -> ProbDist<QuantDist<THit>> {
-    use std::convert::TryInto;
-
+) -> ProbDist<QuantDist<THit>, TProb> {
     // This uses a fairly complex recursive algorithm exhaustively generate all possibilities.
 
     // First we generate the probabilities for each the number of each hit.
@@ -45,66 +62,114 @@ pub fn roll_hits<TUnit: Unit, THit: Hit<TUnit>>(
         let roll = quant.item;
         let roll_count = quant.count;
         let hit = roll.hit;
-        let p = roll.strength as f64 / 6.0;
-        let binomial = Binomial::new(p, roll_count as u64).unwrap();
+        let masses = TProb::binomial(roll.successes() as u32, roll.sides as u32, roll_count);
 
         let mut dist = ProbDistBuilder::with_capacity(roll_count as usize);
-        for hit_count in 0..=roll_count {
-            let mass = binomial.pmf(hit_count as u64);
-            dist.add(hit_count, mass.try_into().unwrap());
+        for (hit_count, mass) in masses.into_iter().enumerate() {
+            dist.add(hit_count as u32, mass);
         }
-        let dist = dist.build();
+        add_hit_dist(&mut hit_dists, hit, dist.build());
+    }
 
-        let entry = hit_dists.entry(hit);
-        match entry {
-            Entry::Occupied(mut existing) => {
-                existing.insert(combine_dists(existing.get(), &dist));
-            }
-            Entry::Vacant(vacant) => {
-                vacant.insert(dist);
-            }
+    expand_hit_dists(&hit_dists)
+}
+
+/// Expands the rolls into a probability distribution of all the hits that could occur under
+/// Low Luck rules.
+///
+/// The strength of every roll of the same hit and die size is totaled. Each full die's worth of
+/// strength is a guaranteed hit, and the remainder is rolled on a single die.
+pub fn roll_hits_low_luck<TUnit: Unit, THit: Hit<TUnit>, TProb: ProbValue>(
+    strike: &QuantDist<Roll<TUnit, THit>>,
+) -> ProbDist<QuantDist<THit>, TProb> {
+    let mut hit_dists = HashMap::with_capacity(strike.outcomes().len());
+    for ((hit, sides), total) in low_luck_totals(strike) {
+        let sides = sides as u32;
+        let guaranteed = total / sides;
+        let remainder = total % sides;
+
+        let mut dist = ProbDistBuilder::with_capacity(2);
+        dist.add(guaranteed, TProb::from_ratio(sides - remainder, sides));
+        dist.add(guaranteed + 1, TProb::from_ratio(remainder, sides));
+        add_hit_dist(&mut hit_dists, hit, dist.build());
+    }
+
+    expand_hit_dists(&hit_dists)
+}
+
+/// Totals the strength of the rolls for each hit and die size.
+fn low_luck_totals<TUnit: Unit, THit: Hit<TUnit>>(
+    strike: &QuantDist<Roll<TUnit, THit>>,
+) -> BTreeMap<(THit, u8), u32> {
+    // Ordered, so that sampling consumes random numbers in a consistent order.
+    let mut totals = BTreeMap::new();
+    for quant in strike.outcomes() {
+        let roll = quant.item;
+        *totals.entry((roll.hit, roll.sides)).or_insert(0) += roll.successes() as u32 * quant.count;
+    }
+    totals
+}
+
+fn add_hit_dist<THit: Eq + Hash, TProb: ProbValue>(
+    hit_dists: &mut HashMap<THit, ProbDist<u32, TProb>>,
+    hit: THit,
+    dist: ProbDist<u32, TProb>,
+) {
+    match hit_dists.entry(hit) {
+        Entry::Occupied(mut existing) => {
+            existing.insert(combine_dists(existing.get(), &dist));
+        }
+        Entry::Vacant(vacant) => {
+            vacant.insert(dist);
         }
     }
+}
 
+fn expand_hit_dists<TUnit: Unit, THit: Hit<TUnit>, TProb: ProbValue>(
+    hit_dists: &HashMap<THit, ProbDist<u32, TProb>>,
+) -> ProbDist<QuantDist<THit>, TProb> {
     // Recursively permutate the distributions of each different hit.
     let mut results = ProbDistBuilder::new();
     combine_hit_dists(
-        &mut hit_dists.iter(),
+        &hit_dists.iter(),
         &mut Vec::new(),
-        Probability::one(),
+        TProb::one(),
         &mut results,
     );
     results.build()
 }
 
-fn combine_dists(destination: &ProbDist<u32>, source: &ProbDist<u32>) -> ProbDist<u32> {
+fn combine_dists<TProb: ProbValue>(
+    destination: &ProbDist<u32, TProb>,
+    source: &ProbDist<u32, TProb>,
+) -> ProbDist<u32, TProb> {
     let mut result = ProbDistBuilder::with_capacity(destination.len());
     for first in destination.outcomes() {
         for second in source.outcomes() {
             let hit_count = first.item + second.item;
-            let p = first.p * second.p;
+            let p = first.p.clone() * second.p.clone();
             result.add(hit_count, p);
         }
     }
     result.build()
 }
 
-fn combine_hit_dists<TUnit: Unit, THit: Hit<TUnit>>(
-    hit_dists: &mut Iter<THit, ProbDist<u32>>,
+fn combine_hit_dists<TUnit: Unit, THit: Hit<TUnit>, TProb: ProbValue>(
+    hit_dists: &Iter<THit, ProbDist<u32, TProb>>,
     hit_stack: &mut Vec<Quant<THit>>,
-    current_p: Probability,
-    results: &mut ProbDistBuilder<QuantDist<THit>>,
+    current_p: TProb,
+    results: &mut ProbDistBuilder<QuantDist<THit>, TProb>,
 ) {
-    match hit_dists.next() {
+    // Each outcome of this hit must be combined with every outcome of the remaining hits, so
+    // each level of recursion advances its own copy of the iterator.
+    let mut remaining = hit_dists.clone();
+    match remaining.next() {
         None => {
             let mut builder = QuantDistBuilder::with_capacity(hit_stack.len());
             for hit in hit_stack.iter() {
                 builder.add(hit.item, hit.count);
             }
             results.add(builder.build(), current_p);
-            if hit_stack.is_empty() {
-                return;
-            }
         }
         Some((hit, dist)) => {
             for prob in dist.outcomes() {
@@ -112,10 +177,42 @@ fn combine_hit_dists<TUnit: Unit, THit: Hit<TUnit>>(
                     item: *hit,
                     count: prob.item,
                 });
-                let next_p = current_p * prob.p;
-                combine_hit_dists(hit_dists, hit_stack, next_p, results);
+                let next_p = current_p.clone() * prob.p.clone();
+                combine_hit_dists(&remaining, hit_stack, next_p, results);
                 hit_stack.pop();
             }
         }
     }
 }
+
+/// Rolls a die for each roll in the strike and returns the hits that actually occurred.
+pub fn sample_hits<TUnit: Unit, THit: Hit<TUnit>, R: rand::Rng + ?Sized>(
+    strike: &QuantDist<Roll<TUnit, THit>>,
+    rng: &mut R,
+) -> QuantDist<THit> {
+    let mut hits = QuantDistBuilder::with_capacity(strike.len());
+    for quant in strike.outcomes() {
+        let roll = quant.item;
+        let hit_count = (0..quant.count)
+            .filter(|_| rng.gen_range(0, roll.sides) < roll.successes())
+            .count();
+        hits.add(roll.hit, hit_count as u32);
+    }
+    hits.build()
+}
+
+/// Rolls the remainder die for each hit and die size under Low Luck rules and returns the hits
+/// that actually occurred.
+pub fn sample_hits_low_luck<TUnit: Unit, THit: Hit<TUnit>, R: rand::Rng + ?Sized>(
+    strike: &QuantDist<Roll<TUnit, THit>>,
+    rng: &mut R,
+) -> QuantDist<THit> {
+    let mut hits = QuantDistBuilder::with_capacity(strike.len());
+    for ((hit, sides), total) in low_luck_totals(strike) {
+        let guaranteed = total / sides as u32;
+        let remainder = total % sides as u32;
+        let extra = (rng.gen_range(0, sides as u32) < remainder) as u32;
+        hits.add(hit, guaranteed + extra);
+    }
+    hits.build()
+}
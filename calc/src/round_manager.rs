@@ -6,30 +6,45 @@ pub struct RoundManager<
     TUnit: Unit,
     THit: Hit<TUnit>,
     TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
-    TSurvivorSelector: SurvivorSelector<TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue = Probability,
 > {
-    combat_manager: CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector>,
+    combat_manager:
+        CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>,
     sequence: PhaseSequence<TBattlePhase>,
-    prune_threshold: Probability,
+    pruning_strategy: Box<dyn PruningStrategy<TProb>>,
+    retreat_policy: Option<Box<dyn RetreatPolicy<TBattlePhase, TUnit>>>,
+    round_limit: usize,
+    track_predecessors: bool,
     round_index: usize,
-    last_round: RoundResult<TBattlePhase, TUnit>,
-    last_probability: Probability,
-    probability_run_count: usize,
+    last_round: RoundResult<TBattlePhase, TUnit, TProb>,
 }
 
-impl<TBattlePhase, THit, TUnit, TRollSelector, TSurvivorSelector>
-    RoundManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector>
+impl<TBattlePhase, THit, TUnit, TRollSelector, TSurvivorSelector, TProb>
+    RoundManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>
 where
     TBattlePhase: BattlePhase,
     TUnit: Unit,
     THit: Hit<TUnit>,
     TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
-    TSurvivorSelector: SurvivorSelector<TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue,
 {
+    /// The default number of rounds after which any pending combats are cut off, and counted as
+    /// pruned.
+    pub const DEFAULT_ROUND_LIMIT: usize = 1000;
+
     /// Constructs a new `RoundManager` with the given `CombatManager`, `PhaseSequence`,
     /// attacking force, and defending force.
     pub fn new(
-        combat_manager: CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector>,
+        combat_manager: CombatManager<
+            TBattlePhase,
+            TUnit,
+            THit,
+            TRollSelector,
+            TSurvivorSelector,
+            TProb,
+        >,
         sequence: PhaseSequence<TBattlePhase>,
         attackers: Force<TUnit>,
         defenders: Force<TUnit>,
@@ -39,60 +54,138 @@ where
         RoundManager {
             combat_manager,
             sequence,
-            prune_threshold: Default::default(),
+            pruning_strategy: Box::new(Pruner::new(TProb::zero())),
+            retreat_policy: None,
+            round_limit: Self::DEFAULT_ROUND_LIMIT,
+            track_predecessors: false,
             round_index,
             last_round: RoundResult::new_initial(first_phase, attackers, defenders),
-            last_probability: Probability::zero(),
-            probability_run_count: 0,
+        }
+    }
+
+    /// Resumes a `RoundManager` from a checkpoint, using the given `CombatManager` and
+    /// `PhaseSequence`, which must match those the checkpoint was saved with.
+    ///
+    /// If the checkpoint has no pruning settings, nothing is pruned until a strategy is set
+    /// again. Any retreat policy must be set again as well.
+    pub fn resume(
+        combat_manager: CombatManager<
+            TBattlePhase,
+            TUnit,
+            THit,
+            TRollSelector,
+            TSurvivorSelector,
+            TProb,
+        >,
+        sequence: PhaseSequence<TBattlePhase>,
+        checkpoint: RoundManagerCheckpoint<TBattlePhase, TUnit, TProb>,
+    ) -> Result<Self, CheckpointError> {
+        checkpoint.check_version()?;
+        let pruning_strategy = match checkpoint.pruning {
+            Some(settings) => settings.into_strategy(),
+            None => Box::new(Pruner::new(TProb::zero())),
+        };
+        Ok(RoundManager {
+            combat_manager,
+            sequence,
+            pruning_strategy,
+            retreat_policy: None,
+            round_limit: checkpoint.round_limit,
+            track_predecessors: checkpoint.track_predecessors,
+            round_index: checkpoint.round_index,
+            last_round: checkpoint.last_round,
+        })
+    }
+
+    /// Saves a checkpoint of the battle so far, from which it can later be resumed.
+    pub fn checkpoint(&self) -> RoundManagerCheckpoint<TBattlePhase, TUnit, TProb> {
+        RoundManagerCheckpoint {
+            version: CHECKPOINT_VERSION,
+            round_index: self.round_index,
+            round_limit: self.round_limit,
+            track_predecessors: self.track_predecessors,
+            pruning: self.pruning_strategy.settings(),
+            last_round: self.last_round.clone(),
         }
     }
 
     /// Computes the next round of the battle and returns the result.
-    pub fn advance_round(&mut self) -> &RoundResult<TBattlePhase, TUnit> {
+    pub fn advance_round(&mut self) -> &RoundResult<TBattlePhase, TUnit, TProb> {
         self.round_processor().finish()
     }
 
+    /// Computes the next round of the battle across the rayon thread pool and returns the result.
+    #[cfg(feature = "parallel")]
+    pub fn advance_round_parallel(&mut self) -> &RoundResult<TBattlePhase, TUnit, TProb>
+    where
+        TBattlePhase: Send + Sync,
+        TUnit: Send + Sync,
+        THit: Send + Sync,
+        TRollSelector: Sync,
+        TSurvivorSelector: Sync,
+        TProb: Send + Sync,
+    {
+        self.round_processor().finish_parallel()
+    }
+
     /// Returns a `RoundProcessor`, enabling piecemeal processing of a large round.
     pub fn round_processor(
         &mut self,
-    ) -> RoundProcessor<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector> {
+    ) -> RoundProcessor<'_, TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>
+    {
         let round_index = self.round_index + 1;
         let next_battle_phase = self.sequence.combat_at(round_index + 1);
-        let pruner = Pruner::new(self.prune_threshold);
-        let builder = RoundResultBuilder::new(round_index, next_battle_phase, pruner);
+        let pruner = self.pruning_strategy.pruner();
+        let mut builder = RoundResultBuilder::new(round_index, next_battle_phase, pruner);
+        if self.track_predecessors {
+            builder.track_predecessors(&self.last_round);
+        }
         RoundProcessor::new(builder, self)
     }
 
-    #[allow(clippy::float_cmp)]
     fn finish_round(
         &mut self,
-        mut result: RoundResult<TBattlePhase, TUnit>,
-    ) -> &RoundResult<TBattlePhase, TUnit> {
+        mut result: RoundResult<TBattlePhase, TUnit, TProb>,
+    ) -> &RoundResult<TBattlePhase, TUnit, TProb> {
         self.round_index += 1;
-        // We check if the current probability and the last probability are *exactly* the same;
-        // if so, this may mean that we're reaching a stalemate: a point where neither side can
-        // hit each other. If this happens 4 times in a row, we consider ourselves to be
-        // stalemated and mark the result accordingly.
-        const STALEMATE_THRESHOLD: usize = 4;
-        if result.total_probability() == self.last_probability {
-            self.probability_run_count += 1;
-            result.stalemate = self.probability_run_count >= STALEMATE_THRESHOLD;
-        } else {
-            self.probability_run_count = 0;
-            self.last_probability = result.total_probability();
+        if let Some(policy) = &self.retreat_policy {
+            if self.sequence.is_cycle_end(self.round_index) {
+                let rounds_completed = self.sequence.cycles_completed(self.round_index);
+                result.retreat_where(|combat| policy.should_retreat(combat, rounds_completed));
+            }
+        }
+
+        if self.sequence.is_cycle_end(self.round_index) {
+            let combat_manager = &self.combat_manager;
+            let sequence = &self.sequence;
+            result.stalemate_where(|combat| combat_manager.is_stalemated(combat, sequence));
+        }
+        // Without pruning, the probability of a pending combat can bottom out at the smallest
+        // representable value instead of reaching zero, so the battle must be cut off somewhere.
+        if self.round_index >= self.round_limit {
+            result.truncate();
         }
+        result.prune_pending(self.pruning_strategy.as_mut());
 
         self.last_round = result;
         &self.last_round
     }
 
+    /// Consumes the `RoundManager`, returning its `CombatManager` so that it can be reused for
+    /// another battle.
+    pub fn into_combat_manager(
+        self,
+    ) -> CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb> {
+        self.combat_manager
+    }
+
     /// Gets the current round index.
     pub fn round_index(&self) -> usize {
         self.round_index
     }
 
     /// Gets the result of the last round that was computed.
-    pub fn last_round(&self) -> &RoundResult<TBattlePhase, TUnit> {
+    pub fn last_round(&self) -> &RoundResult<TBattlePhase, TUnit, TProb> {
         &self.last_round
     }
 
@@ -102,8 +195,43 @@ where
     }
 
     /// Sets the pruning threshold, where outcomes with a probability equal to or below are pruned.
-    pub fn set_prune_threshold(&mut self, p: Probability) {
-        self.prune_threshold = p;
+    ///
+    /// This is shorthand for `set_pruning_strategy` with a `Pruner`.
+    pub fn set_prune_threshold(&mut self, p: TProb) {
+        self.set_pruning_strategy(Pruner::new(p));
+    }
+
+    /// Sets the strategy deciding which outcomes are too unlikely to be worth tracking.
+    pub fn set_pruning_strategy<TPruningStrategy>(&mut self, strategy: TPruningStrategy)
+    where
+        TPruningStrategy: PruningStrategy<TProb> + 'static,
+    {
+        self.pruning_strategy = Box::new(strategy);
+    }
+
+    /// Sets the number of rounds after which any pending combats are cut off, and counted as
+    /// pruned.
+    pub fn set_round_limit(&mut self, round_limit: usize) {
+        self.round_limit = round_limit;
+    }
+
+    /// Sets whether or not the most likely predecessor of each combat is tracked, so that the
+    /// most likely path to an outcome can be rebuilt with `Summarizer::most_likely_path`.
+    pub fn set_track_predecessors(&mut self, track_predecessors: bool) {
+        self.track_predecessors = track_predecessors;
+    }
+
+    /// Sets the policy deciding when the attackers retreat from a pending combat.
+    pub fn set_retreat_policy<TRetreatPolicy>(&mut self, policy: TRetreatPolicy)
+    where
+        TRetreatPolicy: RetreatPolicy<TBattlePhase, TUnit> + 'static,
+    {
+        self.retreat_policy = Some(Box::new(policy));
+    }
+
+    /// Removes the retreat policy, so that the attackers fight until the battle is complete.
+    pub fn clear_retreat_policy(&mut self) {
+        self.retreat_policy = None;
     }
 }
 
@@ -116,11 +244,12 @@ pub struct RoundProcessor<
     TUnit: Unit,
     THit: Hit<TUnit>,
     TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
-    TSurvivorSelector: SurvivorSelector<TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue = Probability,
 > {
-    builder: RoundResultBuilder<TBattlePhase, TUnit>,
+    builder: RoundResultBuilder<TBattlePhase, TUnit, TProb>,
     round_manager:
-        &'a mut RoundManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector>,
+        &'a mut RoundManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>,
     processed_count: usize,
 }
 
@@ -130,18 +259,20 @@ impl<
         TUnit: Unit,
         THit: Hit<TUnit>,
         TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
-        TSurvivorSelector: SurvivorSelector<TUnit, THit>,
-    > RoundProcessor<'a, TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector>
+        TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+        TProb: ProbValue,
+    > RoundProcessor<'a, TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>
 {
     /// Constructs a new `RoundProcessor`.
     fn new(
-        builder: RoundResultBuilder<TBattlePhase, TUnit>,
+        builder: RoundResultBuilder<TBattlePhase, TUnit, TProb>,
         round_manager: &'a mut RoundManager<
             TBattlePhase,
             TUnit,
             THit,
             TRollSelector,
             TSurvivorSelector,
+            TProb,
         >,
     ) -> Self {
         Self {
@@ -183,17 +314,72 @@ impl<
         }
         self.processed_count += count;
 
-        return self.processed_count == self.outcomes().len();
+        self.processed_count == self.outcomes().len()
     }
 
     /// Processes the remaining outcomes, updates the parent `RoundManager`, and returns the result.
-    pub fn finish(mut self) -> &'a RoundResult<TBattlePhase, TUnit> {
+    pub fn finish(mut self) -> &'a RoundResult<TBattlePhase, TUnit, TProb> {
         self.process(self.outcomes().len() - self.processed_count);
         let result = self.builder.build();
         self.round_manager.finish_round(result)
     }
 
-    fn outcomes(&self) -> &[Prob<Combat<TBattlePhase, TUnit>>] {
+    fn outcomes(&self) -> &[Prob<Combat<TBattlePhase, TUnit>, TProb>] {
         self.round_manager.last_round.pending.outcomes()
     }
 }
+
+#[cfg(feature = "parallel")]
+impl<
+        'a,
+        TBattlePhase: BattlePhase + Send + Sync,
+        TUnit: Unit + Send + Sync,
+        THit: Hit<TUnit> + Send + Sync,
+        TRollSelector: RollSelector<TBattlePhase, TUnit, THit> + Sync,
+        TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb> + Sync,
+        TProb: ProbValue + Send + Sync,
+    > RoundProcessor<'a, TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>
+{
+    /// Processes up to `limit` number of pending outcomes across the rayon thread pool, until all
+    /// outcomes are processed. Returns whether or not processing is complete.
+    ///
+    /// Each worker resolves its share of the pending outcomes into a partial round result, and
+    /// the partial results are then merged. Aside from floating point rounding, the result is
+    /// identical to `process`.
+    pub fn process_parallel(&mut self, limit: usize) -> bool {
+        use rayon::prelude::*;
+
+        let start = self.processed_count;
+        let end = std::cmp::min(start.saturating_add(limit), self.outcomes().len());
+        let combat_manager = &self.round_manager.combat_manager;
+        let builder = &self.builder;
+        let partial = self.round_manager.last_round.pending.outcomes()[start..end]
+            .par_iter()
+            .fold(
+                || builder.fork(),
+                |mut partial, combat| {
+                    partial.add(combat_manager.resolve(combat));
+                    partial
+                },
+            )
+            .reduce(
+                || builder.fork(),
+                |mut first, second| {
+                    first.merge(second);
+                    first
+                },
+            );
+        self.builder.merge(partial);
+        self.processed_count = end;
+
+        self.processed_count == self.outcomes().len()
+    }
+
+    /// Processes the remaining outcomes across the rayon thread pool, updates the parent
+    /// `RoundManager`, and returns the result.
+    pub fn finish_parallel(mut self) -> &'a RoundResult<TBattlePhase, TUnit, TProb> {
+        self.process_parallel(self.outcomes().len() - self.processed_count);
+        let result = self.builder.build();
+        self.round_manager.finish_round(result)
+    }
+}
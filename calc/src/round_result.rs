@@ -1,42 +1,82 @@
 use crate::{
-    BattlePhase, Combat, CombatResult, Force, Prob, ProbDist, ProbDistBuilder, Probability, Pruner,
-    Unit,
+    add_phase_casualties, BattlePhase, Casualties, Combat, CombatResult, Force, Prob, ProbDist,
+    ProbDistBuilder, ProbValue, Probability, Pruner, PruningStrategy, Unit,
 };
+use fnv::FnvHashMap;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+type PendingCasualties<TBattlePhase, TUnit> =
+    FnvHashMap<Combat<TBattlePhase, TUnit>, BTreeMap<TBattlePhase, Casualties<TUnit>>>;
+
+/// The most likely way a combat was reached from a combat pending in the previous round.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct Predecessor<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue = Probability> {
+    /// The combat in the previous round which led to this one.
+    pub combat: Combat<TBattlePhase, TUnit>,
+    /// The probability of the previous combat resulting in this one.
+    pub p: TProb,
+    /// The probability of the most likely sequence of combats leading to this one.
+    pub path_p: TProb,
+}
 
 /// An aggregate of all all the combat that occurred in a round.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug)]
-pub struct RoundResult<TBattlePhase: BattlePhase, TUnit: Unit> {
+#[derive(Debug, Clone)]
+pub struct RoundResult<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue = Probability> {
     pub index: usize,
-    pub pending: ProbDist<Combat<TBattlePhase, TUnit>>,
-    pub completed: ProbDist<Combat<TBattlePhase, TUnit>>,
-    pub pruned: ProbDist<Combat<TBattlePhase, TUnit>>,
-    pub surviving_attackers: ProbDist<Force<TUnit>>,
-    pub surviving_defenders: ProbDist<Force<TUnit>>,
-    pub total_probability: Probability,
+    pub pending: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub completed: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub retreated: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub stalemated: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub pruned: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub surviving_attackers: ProbDist<Force<TUnit>, TProb>,
+    pub surviving_defenders: ProbDist<Force<TUnit>, TProb>,
+    /// The expected casualties of each side during this round, by the phase they occurred in.
+    /// The casualties suffered in reaching an outcome which was pruned aren't counted.
+    pub casualties: BTreeMap<TBattlePhase, Casualties<TUnit>>,
+    /// The casualties suffered in reaching each pending combat, so that they can be taken back
+    /// out of `casualties` if it's pruned.
+    #[cfg_attr(feature = "serde1", serde(skip, default = "FnvHashMap::default"))]
+    pub(crate) pending_casualties: PendingCasualties<TBattlePhase, TUnit>,
+    /// The most likely predecessor of each combat reached this round, if tracked.
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_pairs"))]
+    pub predecessors:
+        FnvHashMap<Combat<TBattlePhase, TUnit>, Predecessor<TBattlePhase, TUnit, TProb>>,
+    pub total_probability: TProb,
     pub pruned_count: usize,
-    pub pruned_p: Probability,
+    pub pruned_p: TProb,
     pub stalemate: bool,
 }
 
-impl<TBattlePhase: BattlePhase, TUnit: Unit> Default for RoundResult<TBattlePhase, TUnit> {
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue> Default
+    for RoundResult<TBattlePhase, TUnit, TProb>
+{
     fn default() -> Self {
         RoundResult {
             index: 0,
             pending: ProbDist::default(),
             completed: ProbDist::default(),
+            retreated: ProbDist::default(),
+            stalemated: ProbDist::default(),
             pruned: ProbDist::default(),
             surviving_attackers: ProbDist::default(),
             surviving_defenders: ProbDist::default(),
-            total_probability: Probability::zero(),
+            casualties: BTreeMap::new(),
+            pending_casualties: FnvHashMap::default(),
+            predecessors: FnvHashMap::default(),
+            total_probability: TProb::zero(),
             pruned_count: 0,
-            pruned_p: Probability::zero(),
+            pruned_p: TProb::zero(),
             stalemate: false,
         }
     }
 }
 
-impl<TBattlePhase: BattlePhase, TUnit: Unit> RoundResult<TBattlePhase, TUnit> {
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    RoundResult<TBattlePhase, TUnit, TProb>
+{
     /// Constructs a new initial `RoundResult` with the first battle phase, attackers, and defenders.
     ///
     /// This creates a round result representing the result of the pre-battle round. It contains one
@@ -45,7 +85,7 @@ impl<TBattlePhase: BattlePhase, TUnit: Unit> RoundResult<TBattlePhase, TUnit> {
         first_phase: TBattlePhase,
         attackers: Force<TUnit>,
         defenders: Force<TUnit>,
-    ) -> RoundResult<TBattlePhase, TUnit> {
+    ) -> RoundResult<TBattlePhase, TUnit, TProb> {
         RoundResult {
             pending: vec![Prob {
                 item: Combat {
@@ -53,51 +93,199 @@ impl<TBattlePhase: BattlePhase, TUnit: Unit> RoundResult<TBattlePhase, TUnit> {
                     defenders: defenders.clone(),
                     battle_phase: first_phase,
                 },
-                p: Probability::one(),
+                p: TProb::one(),
             }]
             .into(),
             surviving_attackers: vec![Prob {
                 item: attackers,
-                p: Probability::one(),
+                p: TProb::one(),
             }]
             .into(),
             surviving_defenders: vec![Prob {
                 item: defenders,
-                p: Probability::one(),
+                p: TProb::one(),
             }]
             .into(),
-            total_probability: Probability::one(),
+            total_probability: TProb::one(),
             ..Default::default()
         }
     }
 
     /// Indicates whethes or not this round completes the battle.
     pub fn is_complete(&self) -> bool {
-        self.pending.is_empty() || self.stalemate
+        self.pending.is_empty()
     }
 
     /// The probability that this round is reached in battle.
-    pub fn total_probability(&self) -> Probability {
-        self.total_probability
+    pub fn total_probability(&self) -> TProb {
+        self.total_probability.clone()
+    }
+
+    /// Moves each pending combat satisfying `predicate` into the retreated combats.
+    pub fn retreat_where<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&Combat<TBattlePhase, TUnit>) -> bool,
+    {
+        let retreated = std::mem::take(&mut self.retreated);
+        self.retreated = self.move_pending_where(predicate, retreated);
+    }
+
+    /// Moves each pending combat satisfying `predicate` into the stalemated combats, and marks
+    /// this round as a stalemate if any were moved.
+    pub fn stalemate_where<F>(&mut self, predicate: F)
+    where
+        F: FnMut(&Combat<TBattlePhase, TUnit>) -> bool,
+    {
+        let stalemated = std::mem::take(&mut self.stalemated);
+        self.stalemated = self.move_pending_where(predicate, stalemated);
+        self.stalemate = !self.stalemated.is_empty();
+    }
+
+    /// Prunes as many of the least likely pending combats as `strategy` chooses.
+    pub fn prune_pending<S>(&mut self, strategy: &mut S)
+    where
+        S: PruningStrategy<TProb> + ?Sized,
+    {
+        // Once pruning is done, the remaining pending combats will all be resolved.
+        let pending_casualties = std::mem::take(&mut self.pending_casualties);
+        let mut order: Vec<usize> = (0..self.pending.len()).collect();
+        let outcomes = self.pending.outcomes();
+        order.sort_by(|a, b| {
+            outcomes[*a]
+                .p
+                .partial_cmp(&outcomes[*b].p)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        let ascending: Vec<TProb> = order.iter().map(|i| outcomes[*i].p.clone()).collect();
+        let count = std::cmp::min(strategy.prune_count(&ascending), order.len());
+        if count == 0 {
+            return;
+        }
+
+        let mut prunable = vec![false; order.len()];
+        for i in &order[..count] {
+            prunable[*i] = true;
+        }
+        let pending = std::mem::take(&mut self.pending);
+        let mut remaining = ProbDistBuilder::with_capacity(pending.len() - count);
+        let mut pruned: ProbDistBuilder<_, TProb> = std::mem::take(&mut self.pruned).into();
+        for (combat, prunable) in pending.into_outcomes().into_iter().zip(prunable) {
+            if !prunable {
+                remaining.add_prob(combat);
+                continue;
+            }
+            self.pruned_count += 1;
+            self.pruned_p += combat.p.clone();
+            if let Some(casualties) = pending_casualties.get(&combat.item) {
+                add_phase_casualties(&mut self.casualties, casualties, -1.0);
+            }
+            // Only track up to 100 pruned outcomes - otherwise they can get out of control.
+            if pruned.len() < 100 {
+                pruned.add_prob(combat);
+            }
+        }
+        self.pending = remaining.build();
+        self.pruned = pruned.build();
+    }
+
+    fn move_pending_where<F>(
+        &mut self,
+        mut predicate: F,
+        destination: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    ) -> ProbDist<Combat<TBattlePhase, TUnit>, TProb>
+    where
+        F: FnMut(&Combat<TBattlePhase, TUnit>) -> bool,
+    {
+        let pending = std::mem::take(&mut self.pending);
+        let mut remaining = ProbDistBuilder::with_capacity(pending.len());
+        let mut destination: ProbDistBuilder<_, TProb> = destination.into();
+        for combat in pending.into_outcomes() {
+            if predicate(&combat.item) {
+                destination.add_prob(combat);
+            } else {
+                remaining.add_prob(combat);
+            }
+        }
+        self.pending = remaining.build();
+        destination.build()
+    }
+
+    /// Moves every pending combat into the pruned combats, for a battle which is cut off before
+    /// it's complete.
+    pub fn truncate(&mut self) {
+        let pending = std::mem::take(&mut self.pending);
+        let mut pruned: ProbDistBuilder<_, TProb> = std::mem::take(&mut self.pruned).into();
+        for combat in pending.into_outcomes() {
+            self.pruned_p += combat.p.clone();
+            self.pruned_count += 1;
+            if let Some(casualties) = self.pending_casualties.remove(&combat.item) {
+                add_phase_casualties(&mut self.casualties, &casualties, -1.0);
+            }
+            pruned.add_prob(combat);
+        }
+        self.pruned = pruned.build();
     }
 }
 
 /// A builder to incrementally construct a round result.
 #[derive(Debug)]
-pub struct RoundResultBuilder<TBattlePhase: BattlePhase, TUnit: Unit> {
+pub struct RoundResultBuilder<
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    TProb: ProbValue = Probability,
+> {
     index: usize,
-    pending: ProbDistBuilder<Combat<TBattlePhase, TUnit>>,
-    completed: ProbDistBuilder<Combat<TBattlePhase, TUnit>>,
-    pruned: ProbDistBuilder<Combat<TBattlePhase, TUnit>>,
-    surviving_attackers: ProbDistBuilder<Force<TUnit>>,
-    surviving_defenders: ProbDistBuilder<Force<TUnit>>,
+    pending: ProbDistBuilder<Combat<TBattlePhase, TUnit>, TProb>,
+    completed: ProbDistBuilder<Combat<TBattlePhase, TUnit>, TProb>,
+    pruned: ProbDistBuilder<Combat<TBattlePhase, TUnit>, TProb>,
+    surviving_attackers: ProbDistBuilder<Force<TUnit>, TProb>,
+    surviving_defenders: ProbDistBuilder<Force<TUnit>, TProb>,
+    casualties: BTreeMap<TBattlePhase, Casualties<TUnit>>,
+    pending_casualties: PendingCasualties<TBattlePhase, TUnit>,
+    predecessors: Option<PredecessorTracker<TBattlePhase, TUnit, TProb>>,
     next_battle_phase: TBattlePhase,
-    pruner: Pruner,
+    pruner: Pruner<TProb>,
 }
 
-impl<TBattlePhase: BattlePhase, TUnit: Unit> RoundResultBuilder<TBattlePhase, TUnit> {
+/// Tracks the most likely predecessor of each combat added to a `RoundResultBuilder`.
+#[derive(Debug, Clone)]
+struct PredecessorTracker<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue> {
+    /// The probability of the most likely path to each combat pending in the previous round.
+    previous_path_p: Arc<FnvHashMap<Combat<TBattlePhase, TUnit>, TProb>>,
+    predecessors: FnvHashMap<Combat<TBattlePhase, TUnit>, Predecessor<TBattlePhase, TUnit, TProb>>,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    PredecessorTracker<TBattlePhase, TUnit, TProb>
+{
+    fn fork(&self) -> Self {
+        PredecessorTracker {
+            previous_path_p: self.previous_path_p.clone(),
+            predecessors: FnvHashMap::default(),
+        }
+    }
+
+    /// Records `predecessor` for `combat`, unless a more likely one is already known.
+    fn add(
+        &mut self,
+        combat: &Combat<TBattlePhase, TUnit>,
+        predecessor: Predecessor<TBattlePhase, TUnit, TProb>,
+    ) {
+        match self.predecessors.get_mut(combat) {
+            Some(existing) if existing.path_p >= predecessor.path_p => {}
+            Some(existing) => *existing = predecessor,
+            None => {
+                self.predecessors.insert(combat.clone(), predecessor);
+            }
+        }
+    }
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    RoundResultBuilder<TBattlePhase, TUnit, TProb>
+{
     // Constructs a new `RoundResultBuilder`.
-    pub fn new(round_index: usize, next_battle_phase: TBattlePhase, pruner: Pruner) -> Self {
+    pub fn new(round_index: usize, next_battle_phase: TBattlePhase, pruner: Pruner<TProb>) -> Self {
         RoundResultBuilder {
             index: round_index,
             pending: ProbDistBuilder::default(),
@@ -105,13 +293,16 @@ impl<TBattlePhase: BattlePhase, TUnit: Unit> RoundResultBuilder<TBattlePhase, TU
             pruned: ProbDistBuilder::default(),
             surviving_attackers: ProbDistBuilder::default(),
             surviving_defenders: ProbDistBuilder::default(),
+            casualties: BTreeMap::new(),
+            pending_casualties: FnvHashMap::default(),
+            predecessors: None,
             next_battle_phase,
             pruner,
         }
     }
 
     /// Consumes this builder and returns a new RoundResult.
-    pub fn build(self) -> RoundResult<TBattlePhase, TUnit> {
+    pub fn build(self) -> RoundResult<TBattlePhase, TUnit, TProb> {
         let pending = self.pending.build();
         let completed = self.completed.build();
         let pruned = self.pruned.build();
@@ -120,15 +311,23 @@ impl<TBattlePhase: BattlePhase, TUnit: Unit> RoundResultBuilder<TBattlePhase, TU
             .iter()
             .chain(completed.outcomes())
             .chain(pruned.outcomes())
-            .map(|o| o.p)
+            .map(|o| o.p.clone())
             .sum();
         RoundResult {
             index: self.index,
             pending,
             completed,
+            retreated: ProbDist::default(),
+            stalemated: ProbDist::default(),
             pruned,
             surviving_attackers: self.surviving_attackers.build(),
             surviving_defenders: self.surviving_defenders.build(),
+            casualties: self.casualties,
+            pending_casualties: self.pending_casualties,
+            predecessors: self
+                .predecessors
+                .map(|tracker| tracker.predecessors)
+                .unwrap_or_default(),
             total_probability,
             pruned_count: self.pruner.count,
             pruned_p: self.pruner.sum,
@@ -136,43 +335,146 @@ impl<TBattlePhase: BattlePhase, TUnit: Unit> RoundResultBuilder<TBattlePhase, TU
         }
     }
 
+    /// Constructs a new, empty builder for the same round, with a fresh pruner using the same
+    /// threshold. The new builder can be filled independently and later merged back into this one.
+    pub fn fork(&self) -> Self {
+        let mut fork = Self::new(
+            self.index,
+            self.next_battle_phase,
+            Pruner::new(self.pruner.threshold.clone()),
+        );
+        fork.predecessors = self.predecessors.as_ref().map(|tracker| tracker.fork());
+        fork
+    }
+
+    /// Tracks the most likely predecessor of each combat added to this builder, continuing the
+    /// paths through `previous`, the round whose pending combats are being resolved.
+    pub fn track_predecessors(&mut self, previous: &RoundResult<TBattlePhase, TUnit, TProb>) {
+        let previous_path_p = previous
+            .predecessors
+            .iter()
+            .map(|(combat, predecessor)| (combat.clone(), predecessor.path_p.clone()))
+            .collect();
+        self.predecessors = Some(PredecessorTracker {
+            previous_path_p: Arc::new(previous_path_p),
+            predecessors: FnvHashMap::default(),
+        });
+    }
+
+    /// Merges the outcomes of `other` into this builder, consuming `other`.
+    pub fn merge(&mut self, other: Self) {
+        self.pending.merge(other.pending);
+        self.completed.merge(other.completed);
+        for combat in other.pruned.build().into_outcomes() {
+            // Only track up to 100 pruned outcomes - otherwise they can get out of control.
+            if self.pruned.len() >= 100 {
+                break;
+            }
+            self.pruned.add_prob(combat);
+        }
+        self.surviving_attackers.merge(other.surviving_attackers);
+        self.surviving_defenders.merge(other.surviving_defenders);
+        add_phase_casualties(&mut self.casualties, &other.casualties, 1.0);
+        for (combat, casualties) in other.pending_casualties {
+            add_phase_casualties(
+                self.pending_casualties.entry(combat).or_default(),
+                &casualties,
+                1.0,
+            );
+        }
+        if let (Some(tracker), Some(other)) = (&mut self.predecessors, other.predecessors) {
+            for (combat, predecessor) in other.predecessors {
+                tracker.add(&combat, predecessor);
+            }
+        }
+        self.pruner.merge(&other.pruner);
+    }
+
     /// Adds the combat result to this RoundResult builder.
-    pub fn add(&mut self, combat_result: CombatResult<TBattlePhase, TUnit>) {
+    pub fn add(&mut self, combat_result: CombatResult<TBattlePhase, TUnit, TProb>) {
         let attackers = combat_result.surviving_attackers.outcomes();
         let defenders = combat_result.surviving_defenders.outcomes();
-        for attacker in attackers {
-            for defender in defenders {
-                let p = combat_result.probability * attacker.p * defender.p;
-                let combat = Combat {
-                    attackers: attacker.item.clone(),
-                    defenders: defender.item.clone(),
-                    battle_phase: self.next_battle_phase,
-                };
+        let source_path_p = self.predecessors.as_ref().map(|tracker| {
+            tracker
+                .previous_path_p
+                .get(&combat_result.combat)
+                .cloned()
+                .unwrap_or_else(TProb::one)
+        });
+        for survivors in combat_result.surviving.outcomes() {
+            let step_p = survivors.p.clone();
+            let p = combat_result.probability.clone() * step_p.clone();
+            let (attackers, defenders) = &survivors.item;
+            let combat = Combat {
+                attackers: attackers.clone(),
+                defenders: defenders.clone(),
+                battle_phase: self.next_battle_phase,
+            };
 
-                let combat = Prob { item: combat, p };
-                if self.pruner.prune(&combat) {
-                    // Only track up to 100 pruned outcomes - otherwise they can get out of control.
-                    if self.pruned.len() < 100 {
-                        self.pruned.add_prob(combat);
-                    }
-                } else if combat.item.completed() {
-                    self.completed.add_prob(combat);
+            let combat = Prob { item: combat, p };
+            let pruned = self.pruner.prune(&combat);
+            if pruned || !combat.item.completed() {
+                let casualties = Casualties::from_outcome(
+                    &combat_result.combat.attackers,
+                    attackers,
+                    &combat_result.combat.defenders,
+                    defenders,
+                );
+                let p = combat.p.to_f64();
+                if pruned {
+                    // The casualties of the combat include those of its pruned outcomes.
+                    self.casualties
+                        .entry(combat_result.battle_phase)
+                        .or_default()
+                        .add_scaled(&casualties, -p);
                 } else {
-                    self.pending.add_prob(combat);
+                    self.pending_casualties
+                        .entry(combat.item.clone())
+                        .or_default()
+                        .entry(combat_result.battle_phase)
+                        .or_default()
+                        .add_scaled(&casualties, p);
+                }
+            }
+            if pruned {
+                // Only track up to 100 pruned outcomes - otherwise they can get out of control.
+                if self.pruned.len() < 100 {
+                    self.pruned.add_prob(combat);
                 }
+                continue;
+            }
+            if let (Some(tracker), Some(source_path_p)) = (&mut self.predecessors, &source_path_p) {
+                let predecessor = Predecessor {
+                    combat: combat_result.combat.clone(),
+                    path_p: source_path_p.clone() * step_p.clone(),
+                    p: step_p,
+                };
+                tracker.add(&combat.item, predecessor);
+            }
+            if combat.item.completed() {
+                self.completed.add_prob(combat);
+            } else {
+                self.pending.add_prob(combat);
             }
         }
         for attacker in attackers {
             self.surviving_attackers.add(
                 attacker.item.clone(),
-                attacker.p * combat_result.probability,
+                attacker.p.clone() * combat_result.probability.clone(),
             );
         }
         for defender in defenders {
             self.surviving_defenders.add(
                 defender.item.clone(),
-                defender.p * combat_result.probability,
+                defender.p.clone() * combat_result.probability.clone(),
             );
         }
+        self.casualties
+            .entry(combat_result.battle_phase)
+            .or_default()
+            .add_scaled(
+                &combat_result.casualties,
+                combat_result.probability.to_f64(),
+            );
     }
 }
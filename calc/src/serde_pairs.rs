@@ -0,0 +1,57 @@
+//! Serializes hash maps as sequences of key and value pairs, since many formats only support maps
+//! with string keys.
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+
+pub fn serialize<K, V, H, S>(map: &HashMap<K, V, H>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    serializer.collect_seq(map.iter())
+}
+
+pub fn deserialize<'de, K, V, H, D>(deserializer: D) -> Result<HashMap<K, V, H>, D::Error>
+where
+    K: Eq + Hash + Deserialize<'de>,
+    V: Deserialize<'de>,
+    H: BuildHasher + Default,
+    D: Deserializer<'de>,
+{
+    let pairs = Vec::<(K, V)>::deserialize(deserializer)?;
+    Ok(pairs.into_iter().collect())
+}
+
+/// Serializes a list of hash maps, each as a sequence of key and value pairs.
+pub mod vec {
+    use super::*;
+
+    pub fn serialize<K, V, H, S>(
+        maps: &[HashMap<K, V, H>],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error>
+    where
+        K: Serialize,
+        V: Serialize,
+        S: Serializer,
+    {
+        serializer.collect_seq(maps.iter().map(|map| map.iter().collect::<Vec<_>>()))
+    }
+
+    pub fn deserialize<'de, K, V, H, D>(deserializer: D) -> Result<Vec<HashMap<K, V, H>>, D::Error>
+    where
+        K: Eq + Hash + Deserialize<'de>,
+        V: Deserialize<'de>,
+        H: BuildHasher + Default,
+        D: Deserializer<'de>,
+    {
+        let maps = Vec::<Vec<(K, V)>>::deserialize(deserializer)?;
+        Ok(maps
+            .into_iter()
+            .map(|pairs| pairs.into_iter().collect())
+            .collect())
+    }
+}
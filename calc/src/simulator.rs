@@ -0,0 +1,189 @@
+use crate::stats::{SimulationSummary, Summarizer};
+use crate::*;
+use fnv::FnvHashMap;
+use rand::{rngs::StdRng, SeedableRng};
+
+/// Estimates the outcome of a battle by repeatedly fighting it with real dice rolls.
+///
+/// Each round, every trial that is still in progress is resolved by sampling a single outcome
+/// from the `CombatManager`. The trials are then tallied into a `RoundResult`, where the
+/// probability of each outcome is the fraction of trials that reached it, so the results can
+/// be summarized exactly like those of a `RoundManager`.
+///
+/// Given the same seed, the same forces and the same rules, a simulation always produces the
+/// same results.
+pub struct Simulator<
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    THit: Hit<TUnit>,
+    TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue = Probability,
+> {
+    combat_manager:
+        CombatManager<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>,
+    sequence: PhaseSequence<TBattlePhase>,
+    retreat_policy: Option<Box<dyn RetreatPolicy<TBattlePhase, TUnit>>>,
+    rng: StdRng,
+    trials: u32,
+    round_limit: usize,
+    round_index: usize,
+    last_round: RoundResult<TBattlePhase, TUnit, TProb>,
+    pending_trials: FnvHashMap<Combat<TBattlePhase, TUnit>, u32>,
+}
+
+impl<TBattlePhase, THit, TUnit, TRollSelector, TSurvivorSelector, TProb>
+    Simulator<TBattlePhase, TUnit, THit, TRollSelector, TSurvivorSelector, TProb>
+where
+    TBattlePhase: BattlePhase,
+    TUnit: Unit,
+    THit: Hit<TUnit>,
+    TRollSelector: RollSelector<TBattlePhase, TUnit, THit>,
+    TSurvivorSelector: SurvivorSelector<TUnit, THit, TProb>,
+    TProb: ProbValue,
+{
+    /// The default number of rounds after which any unfinished trials are cut off, and counted as
+    /// pruned.
+    pub const DEFAULT_ROUND_LIMIT: usize = 1000;
+
+    /// Constructs a new `Simulator` with the given `CombatManager`, `PhaseSequence`, attacking
+    /// force, and defending force, which will fight the battle `trials` times using a random
+    /// number generator seeded with `seed`.
+    ///
+    /// Panics if `trials` is zero.
+    pub fn new(
+        combat_manager: CombatManager<
+            TBattlePhase,
+            TUnit,
+            THit,
+            TRollSelector,
+            TSurvivorSelector,
+            TProb,
+        >,
+        sequence: PhaseSequence<TBattlePhase>,
+        attackers: Force<TUnit>,
+        defenders: Force<TUnit>,
+        trials: u32,
+        seed: u64,
+    ) -> Self {
+        if trials == 0 {
+            panic!("At least one trial must be run.");
+        }
+
+        let round_index = 0;
+        let first_phase = sequence.combat_at(round_index + 1);
+        let last_round = RoundResult::new_initial(first_phase, attackers, defenders);
+        let mut pending_trials = FnvHashMap::default();
+        for combat in last_round.pending.outcomes() {
+            pending_trials.insert(combat.item.clone(), trials);
+        }
+        Simulator {
+            combat_manager,
+            sequence,
+            retreat_policy: None,
+            rng: StdRng::seed_from_u64(seed),
+            trials,
+            round_limit: Self::DEFAULT_ROUND_LIMIT,
+            round_index,
+            last_round,
+            pending_trials,
+        }
+    }
+
+    /// Fights the next round of every unfinished trial and returns the result.
+    pub fn advance_round(&mut self) -> &RoundResult<TBattlePhase, TUnit, TProb> {
+        let round_index = self.round_index + 1;
+        let next_battle_phase = self.sequence.combat_at(round_index + 1);
+        // Every outcome reached by a trial is kept, no matter how unlikely.
+        let pruner = Pruner::new(TProb::zero());
+        let mut builder = RoundResultBuilder::new(round_index, next_battle_phase, pruner);
+        let mut pending_trials = FnvHashMap::default();
+
+        let trial_p = TProb::from_ratio(1, self.trials);
+        for combat in self.last_round.pending.outcomes() {
+            let count = self.pending_trials[&combat.item];
+            let combat = Prob::new(combat.item.clone(), trial_p.clone());
+            for _ in 0..count {
+                let result = self.combat_manager.sample(&combat, &mut self.rng);
+                let next = Combat {
+                    battle_phase: next_battle_phase,
+                    attackers: result.surviving_attackers.outcomes()[0].item.clone(),
+                    defenders: result.surviving_defenders.outcomes()[0].item.clone(),
+                };
+                if !next.completed() {
+                    *pending_trials.entry(next).or_insert(0) += 1;
+                }
+                builder.add(result);
+            }
+        }
+
+        let mut result = builder.build();
+        self.round_index = round_index;
+        if let Some(policy) = &self.retreat_policy {
+            if self.sequence.is_cycle_end(round_index) {
+                let rounds_completed = self.sequence.cycles_completed(round_index);
+                result.retreat_where(|combat| policy.should_retreat(combat, rounds_completed));
+            }
+        }
+        if self.sequence.is_cycle_end(round_index) {
+            let combat_manager = &self.combat_manager;
+            let sequence = &self.sequence;
+            result.stalemate_where(|combat| combat_manager.is_stalemated(combat, sequence));
+        }
+        if round_index >= self.round_limit {
+            result.truncate();
+        }
+
+        self.pending_trials = pending_trials;
+        self.last_round = result;
+        &self.last_round
+    }
+
+    /// Fights every trial to completion, and summarizes the results.
+    pub fn run(mut self) -> SimulationSummary<TBattlePhase, TUnit, TProb> {
+        let mut summarizer = Summarizer::new(&self.last_round);
+        while !self.is_complete() {
+            summarizer.add_round(self.advance_round());
+        }
+        SimulationSummary::new(summarizer.summarize(), self.trials)
+    }
+
+    /// Gets the current round index.
+    pub fn round_index(&self) -> usize {
+        self.round_index
+    }
+
+    /// Gets the result of the last round that was fought.
+    pub fn last_round(&self) -> &RoundResult<TBattlePhase, TUnit, TProb> {
+        &self.last_round
+    }
+
+    /// Indicates whether or not every trial is complete.
+    pub fn is_complete(&self) -> bool {
+        self.last_round.is_complete()
+    }
+
+    /// Gets the number of times the battle is fought.
+    pub fn trials(&self) -> u32 {
+        self.trials
+    }
+
+    /// Sets the number of rounds after which any unfinished trials are cut off, and counted as
+    /// pruned.
+    pub fn set_round_limit(&mut self, round_limit: usize) {
+        self.round_limit = round_limit;
+    }
+
+    /// Sets the policy deciding when the attackers retreat from a pending combat.
+    pub fn set_retreat_policy<TRetreatPolicy>(&mut self, policy: TRetreatPolicy)
+    where
+        TRetreatPolicy: RetreatPolicy<TBattlePhase, TUnit> + 'static,
+    {
+        self.retreat_policy = Some(Box::new(policy));
+    }
+
+    /// Removes the retreat policy, so that the attackers fight until the battle is complete.
+    pub fn clear_retreat_policy(&mut self) {
+        self.retreat_policy = None;
+    }
+}
@@ -1,9 +1,19 @@
 mod battle_summary;
+mod combat_path;
+mod luck;
 mod round_summary;
+mod simulation_summary;
 mod stat;
 mod summarizer;
+mod tuv_swing;
+mod unit_survival;
 
 pub use battle_summary::*;
+pub use combat_path::*;
+pub use luck::*;
 pub use round_summary::*;
+pub use simulation_summary::*;
 pub use stat::*;
 pub use summarizer::*;
+pub use tuv_swing::*;
+pub use unit_survival::*;
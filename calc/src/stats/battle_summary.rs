@@ -1,46 +1,298 @@
 use super::*;
 use crate::*;
+use std::collections::{BTreeMap, BTreeSet};
 
 /// A summary of an entire battle.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
-pub struct BattleSummary<TBattlePhase: BattlePhase, TUnit: Unit> {
-    pub prebattle: RoundSummary,
-    pub round_summaries: Vec<RoundSummary>,
-    pub attacker: BattleSideSummary,
-    pub defender: BattleSideSummary,
-    pub completed_combats: ProbDist<Combat<TBattlePhase, TUnit>>,
-    pub draw_p: Probability,
-    pub total_p: Probability,
-    pub pruned_p: Probability,
+pub struct BattleSummary<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue = Probability> {
+    pub prebattle: RoundSummary<TBattlePhase, TUnit, TProb>,
+    /// The combat before the battle, with both forces at full strength.
+    pub prebattle_combat: Combat<TBattlePhase, TUnit>,
+    pub round_summaries: Vec<RoundSummary<TBattlePhase, TUnit, TProb>>,
+    pub attacker: BattleSideSummary<TProb>,
+    pub defender: BattleSideSummary<TProb>,
+    pub completed_combats: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub retreated_combats: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub stalemated_combats: ProbDist<Combat<TBattlePhase, TUnit>, TProb>,
+    pub draw_p: TProb,
+    pub retreat_p: TProb,
+    pub stalemate_p: TProb,
+    /// The probability of the attackers capturing the territory, which unlike
+    /// `attacker.win_p` requires a surviving attacker able to capture it.
+    pub capture_p: TProb,
+    pub tuv_swing: TuvSwing<TProb>,
+    /// The expected casualties of each side over the entire battle, by the phase they occurred
+    /// in.
+    pub casualties: BTreeMap<TBattlePhase, Casualties<TUnit>>,
+    pub total_p: TProb,
+    pub pruned_p: TProb,
 }
 
-impl<TBattlePhase: BattlePhase, TUnit: Unit> BattleSummary<TBattlePhase, TUnit> {
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    BattleSummary<TBattlePhase, TUnit, TProb>
+{
     /// Gets the number of rounds that took place in the battle.
     pub fn round_count(&self) -> usize {
         self.round_summaries.len()
     }
 
     /// Gets the summary for the prebattle round.
-    pub fn prebattle(&self) -> &RoundSummary {
+    pub fn prebattle(&self) -> &RoundSummary<TBattlePhase, TUnit, TProb> {
         &self.prebattle
     }
 
     /// Gets the summary for the last round in the battle, or None if there weren't any rounds.
-    pub fn last_round(&self) -> Option<&RoundSummary> {
+    pub fn last_round(&self) -> Option<&RoundSummary<TBattlePhase, TUnit, TProb>> {
         self.round_summaries.last()
     }
+
+    /// Gets a summary of each game round of the battle, where the rounds are combined according
+    /// to `PhaseSequence::game_round_at`. The index of each summary is its game round.
+    pub fn game_round_summaries(
+        &self,
+        sequence: &PhaseSequence<TBattlePhase>,
+    ) -> Vec<RoundSummary<TBattlePhase, TUnit, TProb>> {
+        let mut summaries: Vec<RoundSummary<TBattlePhase, TUnit, TProb>> = Vec::new();
+        for summary in &self.round_summaries {
+            let game_round = sequence.game_round_at(summary.index);
+            match summaries.last_mut() {
+                Some(last) if last.index == game_round => *last = last.merge(summary, game_round),
+                _ => summaries.push(RoundSummary {
+                    index: game_round,
+                    ..summary.clone()
+                }),
+            }
+        }
+        summaries
+    }
+
+    /// Gets the expected IPC value of the units lost by `side` during `battle_phase`.
+    pub fn casualty_ipc(&self, battle_phase: TBattlePhase, side: Side) -> f64 {
+        self.casualties
+            .get(&battle_phase)
+            .map_or(0.0, |casualties| casualties.ipc(side))
+    }
+
+    /// Gets the distribution of the number of game rounds the battle lasts, sorted in ascending
+    /// order. Stalemated battles never end, so they aren't included.
+    pub fn length_dist(&self, sequence: &PhaseSequence<TBattlePhase>) -> ProbDist<usize, TProb> {
+        let mut length_dist: ProbDist<_, _> = self
+            .game_round_summaries(sequence)
+            .into_iter()
+            .map(|summary| Prob::new(summary.index, summary.ended_p()))
+            .collect::<Vec<_>>()
+            .into();
+        length_dist.sort();
+        length_dist
+    }
+
+    /// Gets the expected number of game rounds the battle lasts, given that it ends.
+    pub fn expected_length(&self, sequence: &PhaseSequence<TBattlePhase>) -> f64 {
+        let length_dist = self.length_dist(sequence);
+        let total_p = length_dist.total_p().to_f64();
+        if total_p == 0.0 {
+            return 0.0;
+        }
+        length_dist
+            .outcomes()
+            .iter()
+            .map(|o| o.item as f64 * o.p.to_f64())
+            .sum::<f64>()
+            / total_p
+    }
+
+    /// Gets the survival of `unit` on the given side, across every way the battle ended.
+    pub fn unit_survival(&self, side: Side, unit: &TUnit) -> UnitSurvival<TProb> {
+        UnitSurvival::from_combats(self.final_combats(), unit, side)
+    }
+
+    /// Gets the survival of each type of unit on the given side before the battle, along with any
+    /// type of unit it turned into during the battle, ordered by unit.
+    pub fn unit_survivals(&self, side: Side) -> Vec<(TUnit, UnitSurvival<TProb>)> {
+        let prebattle = std::iter::once(&self.prebattle_combat);
+        let units: BTreeSet<TUnit> = prebattle
+            .chain(self.final_combats().map(|combat| &combat.item))
+            .flat_map(|combat| {
+                let force = match side {
+                    Side::Attacker => &combat.attackers,
+                    Side::Defender => &combat.defenders,
+                };
+                force.outcomes().iter().map(|quant| quant.item)
+            })
+            .collect();
+        units
+            .into_iter()
+            .map(|unit| (unit, self.unit_survival(side, &unit)))
+            .collect()
+    }
+
+    /// Gets the TUV swing of the battle ending with `combat`: the IPC lost by the defenders minus
+    /// the IPC lost by the attackers.
+    pub fn swing_of(&self, combat: &Combat<TBattlePhase, TUnit>) -> i64 {
+        let (attacker_lost, defender_lost) = ipc_lost(&self.prebattle, combat);
+        defender_lost as i64 - attacker_lost as i64
+    }
+
+    /// Gets how lucky `side` was for the battle to end with the observed `combat`, ranked by
+    /// TUV swing among every way the battle ended.
+    pub fn luck(&self, combat: &Combat<TBattlePhase, TUnit>, side: Side) -> Luck<TProb> {
+        Luck::from_swing_dist(&self.tuv_swing.swing_dist, self.swing_of(combat), side)
+    }
+
+    /// Iterates over every combat the battle ended with, whether completed, retreated from, or
+    /// stalemated.
+    fn final_combats(&self) -> impl Iterator<Item = &Prob<Combat<TBattlePhase, TUnit>, TProb>> {
+        self.completed_combats
+            .outcomes()
+            .iter()
+            .chain(self.retreated_combats.outcomes())
+            .chain(self.stalemated_combats.outcomes())
+    }
+
+    /// Gets the bounds of the probability that the attacker wins.
+    pub fn attacker_win_bounds(&self) -> ProbBounds<TProb> {
+        self.bounds(&self.attacker.win_p)
+    }
+
+    /// Gets the bounds of the probability that the defender wins.
+    pub fn defender_win_bounds(&self) -> ProbBounds<TProb> {
+        self.bounds(&self.defender.win_p)
+    }
+
+    /// Gets the bounds of the probability that the battle ends in a draw.
+    pub fn draw_bounds(&self) -> ProbBounds<TProb> {
+        self.bounds(&self.draw_p)
+    }
+
+    /// Gets the bounds of a probability `p` of this battle, found by assuming that either none or
+    /// all of the unresolved outcomes would have ended the same way. These are the pruned
+    /// outcomes, along with any outcomes still pending if the battle isn't complete.
+    pub fn bounds(&self, p: &TProb) -> ProbBounds<TProb> {
+        let pending_p = TProb::one() - self.total_p.clone() - self.pruned_p.clone();
+        ProbBounds {
+            lower: p.clone(),
+            upper: p.clone() + self.pruned_p.clone() + pending_p,
+        }
+    }
+
+    /// Constructs a summary where each probability is scaled so that the outcomes which weren't
+    /// pruned make up the whole battle.
+    ///
+    /// This assumes that the pruned outcomes would have ended the same way as the rest, so the
+    /// renormalized summary has a `total_p` of one and a `pruned_p` of zero. The prebattle and
+    /// round summaries describe each round as it was computed, so they're left unscaled.
+    pub fn renormalized(&self) -> Self {
+        let total_p = &self.total_p;
+        if *total_p == TProb::zero() {
+            return self.clone();
+        }
+
+        BattleSummary {
+            prebattle: self.prebattle.clone(),
+            prebattle_combat: self.prebattle_combat.clone(),
+            round_summaries: self.round_summaries.clone(),
+            attacker: self.attacker.divide(total_p),
+            defender: self.defender.divide(total_p),
+            completed_combats: self.completed_combats.divide(total_p),
+            retreated_combats: self.retreated_combats.divide(total_p),
+            stalemated_combats: self.stalemated_combats.divide(total_p),
+            draw_p: self.draw_p.clone() / total_p.clone(),
+            retreat_p: self.retreat_p.clone() / total_p.clone(),
+            stalemate_p: self.stalemate_p.clone() / total_p.clone(),
+            capture_p: self.capture_p.clone() / total_p.clone(),
+            tuv_swing: self.tuv_swing.divide(total_p),
+            casualties: {
+                let mut casualties = BTreeMap::new();
+                add_phase_casualties(&mut casualties, &self.casualties, 1.0 / total_p.to_f64());
+                casualties
+            },
+            total_p: TProb::one(),
+            pruned_p: TProb::zero(),
+        }
+    }
 }
 
-/// A summary of a side in a battle.
+/// Guaranteed bounds on a probability.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct BattleSideSummary {
+pub struct ProbBounds<TProb = Probability> {
+    /// The lowest the probability could be.
+    pub lower: TProb,
+    /// The highest the probability could be.
+    pub upper: TProb,
+}
+
+impl<TProb: ProbValue> ProbBounds<TProb> {
+    /// Indicates whether or not `p` lies within these bounds.
+    pub fn contains(&self, p: &TProb) -> bool {
+        self.lower <= *p && *p <= self.upper
+    }
+
+    /// Gets the distance between the lower and upper bounds.
+    pub fn width(&self) -> TProb {
+        self.upper.clone() - self.lower.clone()
+    }
+}
+
+/// A summary of a side in a battle.
+///
+/// Each distribution is sorted in ascending order, and covers every way the battle ended -
+/// including retreats and stalemates. Each statistic is taken over the outcomes which were
+/// resolved, so it's unaffected by pruning.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct BattleSideSummary<TProb = Probability> {
     pub ipc: Stat,
     pub ipc_lost: Stat,
     pub unit_count: Stat,
     pub unit_count_lost: Stat,
     pub strength: Stat,
     pub strength_lost: Stat,
-    pub win_p: Probability,
+    pub win_p: TProb,
+    pub ipc_dist: ProbDist<u32, TProb>,
+    pub ipc_lost_dist: ProbDist<u32, TProb>,
+    pub unit_count_dist: ProbDist<u32, TProb>,
+    pub unit_count_lost_dist: ProbDist<u32, TProb>,
+    pub strength_dist: ProbDist<u32, TProb>,
+    pub strength_lost_dist: ProbDist<u32, TProb>,
+}
+
+impl<TProb: ProbValue> BattleSideSummary<TProb> {
+    /// The probability of this side losing more than `ipc` IPC.
+    pub fn ipc_lost_over_p(&self, ipc: u32) -> TProb {
+        TProb::one() - self.ipc_lost_dist.cdf(&ipc)
+    }
+
+    /// The amount of IPC which this side loses no more than with a probability of `q`.
+    pub fn ipc_lost_percentile(&self, q: &TProb) -> u32 {
+        self.ipc_lost_dist.quantile(q).copied().unwrap_or(0)
+    }
+
+    /// Constructs a copy of this summary with each probability divided by `divisor`. The
+    /// statistics are already normalized over the outcomes they were accumulated from, so
+    /// they're kept as is.
+    pub(crate) fn divide(&self, divisor: &TProb) -> Self {
+        BattleSideSummary {
+            win_p: self.win_p.clone() / divisor.clone(),
+            ipc_dist: self.ipc_dist.divide(divisor),
+            ipc_lost_dist: self.ipc_lost_dist.divide(divisor),
+            unit_count_dist: self.unit_count_dist.divide(divisor),
+            unit_count_lost_dist: self.unit_count_lost_dist.divide(divisor),
+            strength_dist: self.strength_dist.divide(divisor),
+            strength_lost_dist: self.strength_lost_dist.divide(divisor),
+            ..self.clone()
+        }
+    }
+}
+
+/// A summary of a side over only the outcomes of a battle which satisfy some condition.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConditionalSummary<TProb = Probability> {
+    /// The summary of the side, conditioned on the outcome.
+    pub summary: BattleSideSummary<TProb>,
+    /// The probability of the outcome, out of the outcomes which were resolved, i.e. neither
+    /// pruned nor still pending.
+    pub p: TProb,
 }
@@ -0,0 +1,21 @@
+use crate::*;
+
+/// A sequence of combats, one per round, through which a battle can progress.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct CombatPath<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue = Probability> {
+    /// Each combat in the path, starting with the initial combat, along with the probability of
+    /// the previous combat resulting in it. The initial combat always has a probability of one.
+    pub steps: Vec<Prob<Combat<TBattlePhase, TUnit>, TProb>>,
+    /// The probability of the battle progressing through exactly this path.
+    pub p: TProb,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    CombatPath<TBattlePhase, TUnit, TProb>
+{
+    /// Gets the combat the path ends with.
+    pub fn last(&self) -> &Combat<TBattlePhase, TUnit> {
+        &self.steps.last().expect("A path is never empty.").item
+    }
+}
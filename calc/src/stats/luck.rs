@@ -0,0 +1,41 @@
+use crate::*;
+
+/// How lucky one side was for a battle to end with an observed outcome.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct Luck<TProb = Probability> {
+    /// The TUV swing of the observed outcome, from the perspective of the side.
+    pub swing: i64,
+    /// The percentile of the observed outcome, where outcomes with the same swing are counted
+    /// as half worse and half better. Zero is the unluckiest possible result, and one the
+    /// luckiest.
+    pub percentile: TProb,
+    /// The probability of the side doing at least as well as the observed outcome.
+    pub at_least_p: TProb,
+}
+
+impl<TProb: ProbValue> Luck<TProb> {
+    /// Ranks the observed `swing` within `swing_dist`, both from the perspective of the attackers,
+    /// for the given side.
+    pub fn from_swing_dist(swing_dist: &ProbDist<i64, TProb>, swing: i64, side: Side) -> Self {
+        // The probability of doing worse, and of doing no better.
+        let (worse_p, no_better_p) = match side {
+            Side::Attacker => (swing_dist.cdf_below(&swing), swing_dist.cdf(&swing)),
+            // The defenders do worse as the swing increases.
+            Side::Defender => (
+                TProb::one() - swing_dist.cdf(&swing),
+                TProb::one() - swing_dist.cdf_below(&swing),
+            ),
+        };
+        let half = TProb::from_ratio(1, 2);
+        let swing = match side {
+            Side::Attacker => swing,
+            Side::Defender => -swing,
+        };
+        Self {
+            swing,
+            percentile: half.clone() * worse_p.clone() + half * no_better_p,
+            at_least_p: TProb::one() - worse_p,
+        }
+    }
+}
@@ -1,43 +1,95 @@
 use super::*;
 use crate::*;
+use std::collections::BTreeMap;
 use std::ops::Sub;
 
 /// A summary of an individual round.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct RoundSummary {
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+pub struct RoundSummary<TBattlePhase: BattlePhase, TUnit: Unit, TProb = Probability> {
     /// The index of this round.
     pub index: usize,
     /// A summary of the attackers.
-    pub attacker: RoundSideSummary,
+    pub attacker: RoundSideSummary<TProb>,
     /// A summary of the defenders.
-    pub defender: RoundSideSummary,
+    pub defender: RoundSideSummary<TProb>,
     /// The probability of a draw during this round.
-    pub draw_p: Probability,
+    pub draw_p: TProb,
+    /// The probability of the attackers retreating during this round.
+    pub retreat_p: TProb,
+    /// The probability of combats being found stalemated during this round.
+    pub stalemate_p: TProb,
     /// The total probability pruned during this round.
-    pub pruned_p: Probability,
+    pub pruned_p: TProb,
+    /// The expected casualties of each side during this round, by the phase they occurred in.
+    pub casualties: BTreeMap<TBattlePhase, Casualties<TUnit>>,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    RoundSummary<TBattlePhase, TUnit, TProb>
+{
+    /// The probability of the battle ending during this round, whether by a side winning, a
+    /// draw, or a retreat. Stalemates aren't included, since a stalemated battle never ends.
+    pub fn ended_p(&self) -> TProb {
+        self.attacker.win_p.clone()
+            + self.defender.win_p.clone()
+            + self.draw_p.clone()
+            + self.retreat_p.clone()
+    }
+
+    /// Combines this summary with the summary of the following round, `next`, as if both were
+    /// one round with the index `index`.
+    ///
+    /// The state of each side is taken from `next`, while the probabilities of events occurring
+    /// during either round, and the casualties of either round, are added together.
+    pub fn merge(&self, next: &Self, index: usize) -> Self {
+        let mut casualties = self.casualties.clone();
+        add_phase_casualties(&mut casualties, &next.casualties, 1.0);
+        RoundSummary {
+            index,
+            attacker: RoundSideSummary {
+                win_p: self.attacker.win_p.clone() + next.attacker.win_p.clone(),
+                ..next.attacker.clone()
+            },
+            defender: RoundSideSummary {
+                win_p: self.defender.win_p.clone() + next.defender.win_p.clone(),
+                ..next.defender.clone()
+            },
+            draw_p: self.draw_p.clone() + next.draw_p.clone(),
+            retreat_p: self.retreat_p.clone() + next.retreat_p.clone(),
+            stalemate_p: self.stalemate_p.clone() + next.stalemate_p.clone(),
+            pruned_p: self.pruned_p.clone() + next.pruned_p.clone(),
+            casualties,
+        }
+    }
 }
 
 /// The delta of two round summaries.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct RoundDelta {
+pub struct RoundDelta<TProb = Probability> {
     /// The index of the subtrahend round.
     pub subtrahend_index: usize,
     /// The index of the minuend round.
     pub minuend_index: usize,
     /// The delta of the attacker summaries.
-    pub attacker_delta: RoundSideDelta,
+    pub attacker_delta: RoundSideDelta<TProb>,
     /// The delta of the defender summaries.
-    pub defender_delta: RoundSideDelta,
+    pub defender_delta: RoundSideDelta<TProb>,
     /// The delta of the draw probability.
-    pub draw_p: Probability,
+    pub draw_p: TProb,
+    /// The delta of the retreat probability.
+    pub retreat_p: TProb,
+    /// The delta of the stalemate probability.
+    pub stalemate_p: TProb,
     /// The delta of the pruned probability.
-    pub pruned_p: Probability,
+    pub pruned_p: TProb,
 }
 
-impl Sub for RoundSummary {
-    type Output = RoundDelta;
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue> Sub
+    for RoundSummary<TBattlePhase, TUnit, TProb>
+{
+    type Output = RoundDelta<TProb>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         RoundDelta {
@@ -46,21 +98,38 @@ impl Sub for RoundSummary {
             attacker_delta: self.attacker - rhs.attacker,
             defender_delta: self.defender - rhs.defender,
             draw_p: self.draw_p - rhs.draw_p,
+            retreat_p: self.retreat_p - rhs.retreat_p,
+            stalemate_p: self.stalemate_p - rhs.stalemate_p,
             pruned_p: self.pruned_p - rhs.pruned_p,
         }
     }
 }
 
-impl<TBattlePhase: BattlePhase, TUnit: Unit> From<&RoundResult<TBattlePhase, TUnit>>
-    for RoundSummary
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    From<&RoundResult<TBattlePhase, TUnit, TProb>> for RoundSummary<TBattlePhase, TUnit, TProb>
 {
-    fn from(result: &RoundResult<TBattlePhase, TUnit>) -> RoundSummary {
+    fn from(
+        result: &RoundResult<TBattlePhase, TUnit, TProb>,
+    ) -> RoundSummary<TBattlePhase, TUnit, TProb> {
         RoundSummary {
             index: result.index,
             attacker: RoundSideSummary::from_round_result(result, Side::Attacker),
             defender: RoundSideSummary::from_round_result(result, Side::Defender),
             draw_p: sum_win_p(result.completed.outcomes(), None),
-            pruned_p: result.pruned_p,
+            retreat_p: result
+                .retreated
+                .outcomes()
+                .iter()
+                .map(|prob| prob.p.clone())
+                .sum(),
+            stalemate_p: result
+                .stalemated
+                .outcomes()
+                .iter()
+                .map(|prob| prob.p.clone())
+                .sum(),
+            pruned_p: result.pruned_p.clone(),
+            casualties: result.casualties.clone(),
         }
     }
 }
@@ -68,7 +137,7 @@ impl<TBattlePhase: BattlePhase, TUnit: Unit> From<&RoundResult<TBattlePhase, TUn
 /// The delta between two `RoundSideSummary`s.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct RoundSideDelta {
+pub struct RoundSideDelta<TProb = Probability> {
     /// The remaining IPC delta.
     pub ipc: Stat,
     /// The remaining unit count delta.
@@ -76,13 +145,13 @@ pub struct RoundSideDelta {
     /// The remaining strength delta.
     pub strength: Stat,
     /// The win probability delta.
-    pub win_p: Probability,
+    pub win_p: TProb,
 }
 
 /// A summary of a specific side in a round.
 #[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct RoundSideSummary {
+pub struct RoundSideSummary<TProb = Probability> {
     /// The sum of the remaining IPC of this side.
     pub ipc: Stat,
     /// The number of remaining units of this side.
@@ -90,13 +159,13 @@ pub struct RoundSideSummary {
     /// The sum of the strength of this side.
     pub strength: Stat,
     /// The probability of this side winning.
-    pub win_p: Probability,
+    pub win_p: TProb,
 }
 
-impl RoundSideSummary {
+impl<TProb: ProbValue> RoundSideSummary<TProb> {
     /// Constructs a new side summary for the given side from the round result.
     pub fn from_round_result<TBattlePhase: BattlePhase, TUnit: Unit>(
-        result: &RoundResult<TBattlePhase, TUnit>,
+        result: &RoundResult<TBattlePhase, TUnit, TProb>,
         side: Side,
     ) -> Self {
         let iter = match side {
@@ -106,7 +175,7 @@ impl RoundSideSummary {
 
         let win_p = sum_win_p(result.completed.outcomes(), Some(side));
 
-        let mut total_p = Probability::zero();
+        let mut total_p = TProb::zero();
 
         let mut ipc = Stat::default();
         let mut unit_count = Stat::default();
@@ -123,11 +192,11 @@ impl RoundSideSummary {
                     let strength = acc.2 + unit.strength(side) as u32 * count;
                     (ipc, unit_count, strength)
                 });
-            let p = prob.p;
-            total_p += p;
-            ipc.add_value(ipc_sum as f64, p, total_p);
-            unit_count.add_value(unit_count_sum, p, total_p);
-            strength.add_value(strength_sum, p, total_p);
+            let p = &prob.p;
+            total_p += p.clone();
+            ipc.add_value(ipc_sum as f64, p, &total_p);
+            unit_count.add_value(unit_count_sum, p, &total_p);
+            strength.add_value(strength_sum, p, &total_p);
         }
 
         Self {
@@ -139,8 +208,8 @@ impl RoundSideSummary {
     }
 }
 
-impl Sub for RoundSideSummary {
-    type Output = RoundSideDelta;
+impl<TProb: ProbValue> Sub for RoundSideSummary<TProb> {
+    type Output = RoundSideDelta<TProb>;
 
     fn sub(self, rhs: Self) -> Self::Output {
         Self::Output {
@@ -152,13 +221,13 @@ impl Sub for RoundSideSummary {
     }
 }
 
-fn sum_win_p<TBattlePhase: BattlePhase, TUnit: Unit>(
-    outcomes: &[Prob<Combat<TBattlePhase, TUnit>>],
+fn sum_win_p<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>(
+    outcomes: &[Prob<Combat<TBattlePhase, TUnit>, TProb>],
     side: Option<Side>,
-) -> Probability {
+) -> TProb {
     outcomes
         .iter()
         .filter(|prob| prob.item.winner() == side)
-        .map(|prob| prob.p)
+        .map(|prob| prob.p.clone())
         .sum()
 }
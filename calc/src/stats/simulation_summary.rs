@@ -0,0 +1,118 @@
+use super::*;
+use crate::*;
+use statrs::distribution::{InverseCDF, Normal};
+use std::fmt::Display;
+
+/// A summary of a simulated battle, along with the number of trials it was estimated from.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulationSummary<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue = Probability>
+{
+    /// The estimated summary of the battle.
+    pub summary: BattleSummary<TBattlePhase, TUnit, TProb>,
+    /// The number of times the battle was fought.
+    pub trials: u32,
+}
+
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    SimulationSummary<TBattlePhase, TUnit, TProb>
+{
+    /// Constructs a new `SimulationSummary` from a summary estimated with `trials` trials.
+    pub fn new(summary: BattleSummary<TBattlePhase, TUnit, TProb>, trials: u32) -> Self {
+        Self { summary, trials }
+    }
+
+    /// The confidence interval of the probability that the attacker wins.
+    pub fn attacker_win_interval(&self, confidence: f64) -> ConfidenceInterval {
+        self.proportion_interval(&self.summary.attacker.win_p, confidence)
+    }
+
+    /// The confidence interval of the probability that the defender wins.
+    pub fn defender_win_interval(&self, confidence: f64) -> ConfidenceInterval {
+        self.proportion_interval(&self.summary.defender.win_p, confidence)
+    }
+
+    /// The confidence interval of the probability that the battle ends in a draw.
+    pub fn draw_interval(&self, confidence: f64) -> ConfidenceInterval {
+        self.proportion_interval(&self.summary.draw_p, confidence)
+    }
+
+    /// The confidence interval of the probability that the attacker retreats.
+    pub fn retreat_interval(&self, confidence: f64) -> ConfidenceInterval {
+        self.proportion_interval(&self.summary.retreat_p, confidence)
+    }
+
+    /// The confidence interval of the probability that the battle is stalemated.
+    pub fn stalemate_interval(&self, confidence: f64) -> ConfidenceInterval {
+        self.proportion_interval(&self.summary.stalemate_p, confidence)
+    }
+
+    /// The confidence interval of an estimated probability, using the Wilson score interval.
+    ///
+    /// Unlike the normal approximation, the Wilson interval remains sensible for probabilities
+    /// near zero or one, which are common in lopsided battles.
+    pub fn proportion_interval(&self, p: &TProb, confidence: f64) -> ConfidenceInterval {
+        let z = z_score(confidence);
+        let n = self.trials as f64;
+        let p = p.to_f64();
+
+        let z2 = z * z;
+        let denominator = 1.0 + z2 / n;
+        let center = (p + z2 / (2.0 * n)) / denominator;
+        let margin = z * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt() / denominator;
+        ConfidenceInterval {
+            lower: (center - margin).max(0.0),
+            upper: (center + margin).min(1.0),
+        }
+    }
+
+    /// The confidence interval of the mean of an estimated statistic, such as the IPC lost by
+    /// the attacker.
+    pub fn mean_interval(&self, stat: &Stat, confidence: f64) -> ConfidenceInterval {
+        let z = z_score(confidence);
+        let margin = z * (stat.variance / self.trials as f64).sqrt();
+        ConfidenceInterval {
+            lower: stat.mean - margin,
+            upper: stat.mean + margin,
+        }
+    }
+}
+
+/// A range of values expected to contain the true value with a certain confidence.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct ConfidenceInterval {
+    /// The lower bound of the interval.
+    pub lower: f64,
+    /// The upper bound of the interval.
+    pub upper: f64,
+}
+
+impl ConfidenceInterval {
+    /// Indicates whether or not `value` lies within this interval.
+    pub fn contains(&self, value: f64) -> bool {
+        (self.lower..=self.upper).contains(&value)
+    }
+
+    /// The distance between the bounds of this interval.
+    pub fn width(&self) -> f64 {
+        self.upper - self.lower
+    }
+}
+
+impl Display for ConfidenceInterval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{:.4}, {:.4}]", self.lower, self.upper)
+    }
+}
+
+/// Returns the two-sided z-score for the given confidence level.
+///
+/// Panics if `confidence` is not strictly between 0 and 1.
+fn z_score(confidence: f64) -> f64 {
+    if confidence <= 0.0 || confidence >= 1.0 {
+        panic!("Confidence must be between 0 and 1, exclusive.");
+    }
+    let normal = Normal::new(0.0, 1.0).unwrap();
+    normal.inverse_cdf(0.5 + confidence / 2.0)
+}
@@ -17,16 +17,34 @@ pub struct Stat {
 
 impl Stat {
     /// Adds the value to the statistic, updating the mean and the variance.
-    pub fn add_value<T: Into<f64>>(&mut self, value: T, p: Probability, total_p: Probability) {
+    pub fn add_value<T: Into<f64>, TProb: ProbValue>(
+        &mut self,
+        value: T,
+        p: &TProb,
+        total_p: &TProb,
+    ) {
         let value: f64 = value.into();
-        let p: f64 = p.into();
-        let total_p: f64 = total_p.into();
+        let p = p.to_f64();
+        let total_p = total_p.to_f64();
 
         let old_mean = self.mean;
         self.mean += (p / total_p) * (value - self.mean);
         self.variance += p * (value - old_mean) * (value - self.mean);
     }
 
+    /// Divides the variance accumulated by `add_value` by the total probability of the values
+    /// added, so that it's the variance given that one of them occurred. The mean is already
+    /// normalized as it's accumulated.
+    pub(crate) fn normalized<TProb: ProbValue>(self, total_p: &TProb) -> Self {
+        if *total_p == TProb::zero() {
+            return self;
+        }
+        Self {
+            variance: self.variance / total_p.to_f64(),
+            ..self
+        }
+    }
+
     /// Gets the standard deviation of the statistic.
     pub fn std_dev(&self) -> f64 {
         self.variance.sqrt()
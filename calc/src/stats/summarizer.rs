@@ -1,110 +1,337 @@
 use super::*;
 use crate::*;
+use fnv::FnvHashMap;
+use std::collections::BTreeMap;
+
+type Predecessors<TBattlePhase, TUnit, TProb> =
+    FnvHashMap<Combat<TBattlePhase, TUnit>, Predecessor<TBattlePhase, TUnit, TProb>>;
 
 /// Summarizes a battle.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, PartialEq)]
-pub struct Summarizer<TBattlePhase: BattlePhase, TUnit: Unit> {
-    prebattle: RoundSummary,
-    round_summaries: Vec<RoundSummary>,
-    attacker_summary: BattleSideBuilder,
-    defender_summary: BattleSideBuilder,
-    completed_combats: ProbDistBuilder<Combat<TBattlePhase, TUnit>>,
-    draw_p: Probability,
-    total_p: Probability,
-    pruned_p: Probability,
+pub struct Summarizer<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue = Probability> {
+    prebattle: RoundSummary<TBattlePhase, TUnit, TProb>,
+    prebattle_combat: Combat<TBattlePhase, TUnit>,
+    round_summaries: Vec<RoundSummary<TBattlePhase, TUnit, TProb>>,
+    attacker_summary: BattleSideBuilder<TProb>,
+    defender_summary: BattleSideBuilder<TProb>,
+    completed_combats: ProbDistBuilder<Combat<TBattlePhase, TUnit>, TProb>,
+    retreated_combats: ProbDistBuilder<Combat<TBattlePhase, TUnit>, TProb>,
+    stalemated_combats: ProbDistBuilder<Combat<TBattlePhase, TUnit>, TProb>,
+    draw_p: TProb,
+    retreat_p: TProb,
+    stalemate_p: TProb,
+    capture_p: TProb,
+    ipc_lost: ProbDistBuilder<(u32, u32), TProb>,
+    casualties: BTreeMap<TBattlePhase, Casualties<TUnit>>,
+    #[cfg_attr(feature = "serde1", serde(with = "crate::serde_pairs::vec"))]
+    predecessors: Vec<Predecessors<TBattlePhase, TUnit, TProb>>,
+    total_p: TProb,
+    pruned_p: TProb,
 }
 
-impl<TBattlePhase: BattlePhase, TUnit: Unit> Summarizer<TBattlePhase, TUnit> {
+impl<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>
+    Summarizer<TBattlePhase, TUnit, TProb>
+{
     /// Creates a new battle summary.
-    pub fn new(prebattle: &RoundResult<TBattlePhase, TUnit>) -> Self {
+    pub fn new(prebattle: &RoundResult<TBattlePhase, TUnit, TProb>) -> Self {
         Self {
             prebattle: prebattle.into(),
+            // There's only one combat before the battle.
+            prebattle_combat: prebattle.pending.outcomes()[0].item.clone(),
             round_summaries: Vec::new(),
             attacker_summary: Default::default(),
             defender_summary: Default::default(),
             completed_combats: Default::default(),
+            retreated_combats: Default::default(),
+            stalemated_combats: Default::default(),
             draw_p: Default::default(),
+            retreat_p: Default::default(),
+            stalemate_p: Default::default(),
+            capture_p: Default::default(),
+            ipc_lost: Default::default(),
+            casualties: Default::default(),
+            predecessors: Vec::new(),
             total_p: Default::default(),
             pruned_p: Default::default(),
         }
     }
 
+    /// Resumes a `Summarizer` from a checkpoint.
+    pub fn resume(
+        checkpoint: SummarizerCheckpoint<TBattlePhase, TUnit, TProb>,
+    ) -> Result<Self, CheckpointError> {
+        checkpoint.check_version()?;
+        Ok(checkpoint.summarizer)
+    }
+
+    /// Saves a checkpoint of the battle so far, from which it can later be resumed.
+    pub fn checkpoint(&self) -> SummarizerCheckpoint<TBattlePhase, TUnit, TProb> {
+        SummarizerCheckpoint::new(self)
+    }
+
     /// Gets the number of rounds that took place in the battle.
     pub fn round_count(&self) -> usize {
         self.round_summaries.len()
     }
 
     /// Gets the summary for the prebattle round.
-    pub fn prebattle(&self) -> &RoundSummary {
+    pub fn prebattle(&self) -> &RoundSummary<TBattlePhase, TUnit, TProb> {
         &self.prebattle
     }
 
+    /// Gets the combat before the battle, with both forces at full strength.
+    pub fn prebattle_combat(&self) -> &Combat<TBattlePhase, TUnit> {
+        &self.prebattle_combat
+    }
+
     /// Gets the summary for the last round in the battle, or None if there weren't any rounds.
-    pub fn last_round(&self) -> Option<&RoundSummary> {
+    pub fn last_round(&self) -> Option<&RoundSummary<TBattlePhase, TUnit, TProb>> {
         self.round_summaries.last()
     }
 
     /// Consumes this summarizer and constructs a new `BattleSummary`.
-    pub fn summarize(self) -> BattleSummary<TBattlePhase, TUnit> {
+    pub fn summarize(self) -> BattleSummary<TBattlePhase, TUnit, TProb> {
         BattleSummary {
+            attacker: self
+                .attacker_summary
+                .build(&self.prebattle.attacker, &self.total_p),
+            defender: self
+                .defender_summary
+                .build(&self.prebattle.defender, &self.total_p),
             prebattle: self.prebattle,
+            prebattle_combat: self.prebattle_combat,
             round_summaries: self.round_summaries,
-            attacker: self.attacker_summary.build(&self.prebattle.attacker),
-            defender: self.defender_summary.build(&self.prebattle.defender),
             completed_combats: self.completed_combats.build(),
+            retreated_combats: self.retreated_combats.build(),
+            stalemated_combats: self.stalemated_combats.build(),
             draw_p: self.draw_p,
+            retreat_p: self.retreat_p,
+            stalemate_p: self.stalemate_p,
+            capture_p: self.capture_p,
+            tuv_swing: TuvSwing::from_ipc_lost(&self.ipc_lost.build()),
+            casualties: self.casualties,
             total_p: self.total_p,
             pruned_p: self.pruned_p,
         }
     }
 
-    pub fn add_round(&mut self, round: &RoundResult<TBattlePhase, TUnit>) -> &RoundSummary {
+    /// Summarizes one side over only the combats the battle ended with which satisfy
+    /// `predicate`, along with the probability of the battle ending that way out of the outcomes
+    /// which were resolved.
+    ///
+    /// Each probability in the summary is conditioned on `predicate`, e.g. its `win_p` is the
+    /// probability of the side winning given that the battle ended in a matching combat.
+    pub fn summarize_where<F>(&self, side: Side, mut predicate: F) -> ConditionalSummary<TProb>
+    where
+        F: FnMut(&Combat<TBattlePhase, TUnit>) -> bool,
+    {
+        let mut builder = BattleSideBuilder::default();
+        let mut p = TProb::zero();
+        let combats = [
+            self.completed_combats.build_cloned(),
+            self.retreated_combats.build_cloned(),
+            self.stalemated_combats.build_cloned(),
+        ];
+        for combat in combats.iter().flat_map(|combats| combats.outcomes()) {
+            if predicate(&combat.item) {
+                p += combat.p.clone();
+                builder.accumulate(&combat.item, &combat.p, &p, side);
+            }
+        }
+
+        let prebattle = match side {
+            Side::Attacker => &self.prebattle.attacker,
+            Side::Defender => &self.prebattle.defender,
+        };
+        let summary = builder.build(prebattle, &p);
+        let summary = if p == TProb::zero() {
+            summary
+        } else {
+            summary.divide(&p)
+        };
+        let p = if self.total_p == TProb::zero() {
+            p
+        } else {
+            p / self.total_p.clone()
+        };
+        ConditionalSummary { summary, p }
+    }
+
+    pub fn add_round(
+        &mut self,
+        round: &RoundResult<TBattlePhase, TUnit, TProb>,
+    ) -> &RoundSummary<TBattlePhase, TUnit, TProb> {
         self.round_summaries.push(round.into());
         self.accumulate_completed(&round.completed);
-        self.pruned_p += round.pruned_p;
+        self.accumulate_retreated(&round.retreated);
+        self.accumulate_stalemated(&round.stalemated);
+        self.pruned_p += round.pruned_p.clone();
+        add_phase_casualties(&mut self.casualties, &round.casualties, 1.0);
+        self.predecessors.push(round.predecessors.clone());
         self.round_summaries.last().unwrap()
     }
 
-    fn accumulate_completed(&mut self, combat: &ProbDist<Combat<TBattlePhase, TUnit>>) {
+    /// Rebuilds the most likely path from the initial combat to `combat`, over every round in
+    /// which `combat` was reached. Returns None if it wasn't reached in any round which tracked
+    /// predecessors, see `RoundManager::set_track_predecessors`.
+    pub fn most_likely_path(
+        &self,
+        combat: &Combat<TBattlePhase, TUnit>,
+    ) -> Option<CombatPath<TBattlePhase, TUnit, TProb>> {
+        let (mut round, mut predecessor) = self
+            .predecessors
+            .iter()
+            .enumerate()
+            .filter_map(|(round, predecessors)| Some((round, predecessors.get(combat)?)))
+            .fold(
+                None,
+                |best: Option<(usize, &Predecessor<_, _, _>)>, next| match best {
+                    Some(best) if best.1.path_p >= next.1.path_p => Some(best),
+                    _ => Some(next),
+                },
+            )?;
+
+        let p = predecessor.path_p.clone();
+        let mut steps = vec![Prob::new(combat.clone(), predecessor.p.clone())];
+        // The predecessors of round `r` are pending in round `r - 1`, and so on back to the
+        // initial combat, which has no predecessor.
+        while let Some(previous) = round
+            .checked_sub(1)
+            .and_then(|previous| self.predecessors[previous].get(&predecessor.combat))
+        {
+            steps.push(Prob::new(predecessor.combat.clone(), previous.p.clone()));
+            predecessor = previous;
+            round -= 1;
+        }
+        steps.push(Prob::new(predecessor.combat.clone(), TProb::one()));
+        steps.reverse();
+        Some(CombatPath { steps, p })
+    }
+
+    /// Rebuilds the most likely path to each of the `n` most likely combats the battle ended
+    /// with, from most to least likely.
+    pub fn most_likely_paths(&self, n: usize) -> Vec<CombatPath<TBattlePhase, TUnit, TProb>> {
+        let combats = [
+            self.completed_combats.build_cloned(),
+            self.retreated_combats.build_cloned(),
+            self.stalemated_combats.build_cloned(),
+        ];
+        let mut outcomes: Vec<_> = combats
+            .iter()
+            .flat_map(|combats| combats.outcomes())
+            .collect();
+        outcomes.sort_by(|a, b| b.p.partial_cmp(&a.p).unwrap_or(std::cmp::Ordering::Equal));
+        outcomes
+            .into_iter()
+            .filter_map(|outcome| self.most_likely_path(&outcome.item))
+            .take(n)
+            .collect()
+    }
+
+    fn accumulate_completed(&mut self, combat: &ProbDist<Combat<TBattlePhase, TUnit>, TProb>) {
         for combat in combat.outcomes() {
             self.completed_combats.add_prob(combat.clone());
             self.accumulate_combat(combat);
+            if combat.item.winner().is_none() {
+                self.draw_p += combat.p.clone();
+            }
+            if combat.item.captured() {
+                self.capture_p += combat.p.clone();
+            }
         }
     }
 
-    fn accumulate_combat(&mut self, combat: &Prob<Combat<TBattlePhase, TUnit>>) {
-        let p = combat.p;
+    fn accumulate_retreated(&mut self, combat: &ProbDist<Combat<TBattlePhase, TUnit>, TProb>) {
+        for combat in combat.outcomes() {
+            self.retreated_combats.add_prob(combat.clone());
+            self.accumulate_combat(combat);
+            self.retreat_p += combat.p.clone();
+        }
+    }
+
+    fn accumulate_stalemated(&mut self, combat: &ProbDist<Combat<TBattlePhase, TUnit>, TProb>) {
+        for combat in combat.outcomes() {
+            self.stalemated_combats.add_prob(combat.clone());
+            self.accumulate_combat(combat);
+            self.stalemate_p += combat.p.clone();
+        }
+    }
+
+    fn accumulate_combat(&mut self, combat: &Prob<Combat<TBattlePhase, TUnit>, TProb>) {
+        let p = &combat.p;
         let combat = &combat.item;
-        self.total_p += p;
+        self.total_p += p.clone();
 
         self.attacker_summary
-            .accumulate(combat, p, self.total_p, Side::Attacker);
+            .accumulate(combat, p, &self.total_p, Side::Attacker);
         self.defender_summary
-            .accumulate(combat, p, self.total_p, Side::Defender);
-        if combat.winner().is_none() {
-            self.draw_p += p;
-        }
+            .accumulate(combat, p, &self.total_p, Side::Defender);
+
+        self.ipc_lost
+            .add(ipc_lost(&self.prebattle, combat), p.clone());
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-struct BattleSideBuilder {
+/// The IPC lost by the attackers and by the defenders for the battle to end with `combat`.
+pub(crate) fn ipc_lost<TBattlePhase: BattlePhase, TUnit: Unit, TProb: ProbValue>(
+    prebattle: &RoundSummary<TBattlePhase, TUnit, TProb>,
+    combat: &Combat<TBattlePhase, TUnit>,
+) -> (u32, u32) {
+    // There's only one combat before the battle, so the mean is the exact value.
+    let attacker_ipc = prebattle.attacker.ipc.mean.round() as u32;
+    let defender_ipc = prebattle.defender.ipc.mean.round() as u32;
+    (
+        attacker_ipc.saturating_sub(force_ipc(&combat.attackers)),
+        defender_ipc.saturating_sub(force_ipc(&combat.defenders)),
+    )
+}
+
+/// The total IPC of every unit in `force`.
+pub(crate) fn force_ipc<TUnit: Unit>(force: &Force<TUnit>) -> u32 {
+    force
+        .outcomes()
+        .iter()
+        .map(|quant| quant.item.ipc() * quant.count)
+        .sum()
+}
+
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+struct BattleSideBuilder<TProb: ProbValue> {
     pub ipc: Stat,
     pub unit_count: Stat,
     pub strength: Stat,
-    pub win_p: Probability,
+    pub win_p: TProb,
+    pub ipc_dist: ProbDistBuilder<u32, TProb>,
+    pub unit_count_dist: ProbDistBuilder<u32, TProb>,
+    pub strength_dist: ProbDistBuilder<u32, TProb>,
+}
+
+impl<TProb: ProbValue> Default for BattleSideBuilder<TProb> {
+    fn default() -> Self {
+        Self {
+            ipc: Default::default(),
+            unit_count: Default::default(),
+            strength: Default::default(),
+            win_p: Default::default(),
+            ipc_dist: Default::default(),
+            unit_count_dist: Default::default(),
+            strength_dist: Default::default(),
+        }
+    }
 }
 
-impl BattleSideBuilder {
+impl<TProb: ProbValue> BattleSideBuilder<TProb> {
     pub fn accumulate<TBattlePhase: BattlePhase, TUnit: Unit>(
         &mut self,
         combat: &Combat<TBattlePhase, TUnit>,
-        p: Probability,
-        total_p: Probability,
+        p: &TProb,
+        total_p: &TProb,
         side: Side,
     ) {
         if combat.winner() == Some(side) {
-            self.win_p += p;
+            self.win_p += p.clone();
         }
 
         let force = match side {
@@ -125,28 +352,60 @@ impl BattleSideBuilder {
         self.ipc.add_value(ipc_sum as f64, p, total_p);
         self.unit_count.add_value(unit_count_sum as f64, p, total_p);
         self.strength.add_value(strength_sum as f64, p, total_p);
+        self.ipc_dist.add(ipc_sum, p.clone());
+        self.unit_count_dist.add(unit_count_sum, p.clone());
+        self.strength_dist.add(strength_sum, p.clone());
     }
 
-    pub fn build(self, prebattle: &RoundSideSummary) -> BattleSideSummary {
+    /// Builds the summary of the side, where `total_p` is the total probability of the combats
+    /// which were accumulated.
+    pub fn build(
+        self,
+        prebattle: &RoundSideSummary<TProb>,
+        total_p: &TProb,
+    ) -> BattleSideSummary<TProb> {
+        let ipc = self.ipc.normalized(total_p);
+        let unit_count = self.unit_count.normalized(total_p);
+        let strength = self.strength.normalized(total_p);
+        let (ipc_dist, ipc_lost_dist) = build_dists(self.ipc_dist, &prebattle.ipc);
+        let (unit_count_dist, unit_count_lost_dist) =
+            build_dists(self.unit_count_dist, &prebattle.unit_count);
+        let (strength_dist, strength_lost_dist) =
+            build_dists(self.strength_dist, &prebattle.strength);
         BattleSideSummary {
-            ipc: self.ipc,
-            ipc_lost: prebattle.ipc - self.ipc,
-            unit_count: self.unit_count,
-            unit_count_lost: prebattle.unit_count - self.unit_count,
-            strength: self.strength,
-            strength_lost: prebattle.strength - self.strength,
+            ipc,
+            ipc_lost: prebattle.ipc - ipc,
+            unit_count,
+            unit_count_lost: prebattle.unit_count - unit_count,
+            strength,
+            strength_lost: prebattle.strength - strength,
             win_p: self.win_p,
+            ipc_dist,
+            ipc_lost_dist,
+            unit_count_dist,
+            unit_count_lost_dist,
+            strength_dist,
+            strength_lost_dist,
         }
     }
 }
 
-impl Default for BattleSideBuilder {
-    fn default() -> Self {
-        Self {
-            ipc: Default::default(),
-            unit_count: Default::default(),
-            win_p: Default::default(),
-            strength: Default::default(),
-        }
-    }
+/// Builds the sorted distribution of a remaining value, along with the sorted distribution of
+/// how much was lost from the value before the battle.
+fn build_dists<TProb: ProbValue>(
+    remaining: ProbDistBuilder<u32, TProb>,
+    prebattle: &Stat,
+) -> (ProbDist<u32, TProb>, ProbDist<u32, TProb>) {
+    // There's only one force before the battle, so the mean is the exact value.
+    let prebattle = prebattle.mean.round() as u32;
+    let mut remaining = remaining.build();
+    remaining.sort();
+    let mut lost: ProbDist<_, _> = remaining
+        .outcomes()
+        .iter()
+        .map(|o| Prob::new(prebattle.saturating_sub(o.item), o.p.clone()))
+        .collect::<Vec<_>>()
+        .into();
+    lost.sort();
+    (remaining, lost)
 }
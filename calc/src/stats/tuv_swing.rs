@@ -0,0 +1,73 @@
+use super::*;
+use crate::*;
+
+/// The swing in total unit value (TUV) of a battle: the IPC lost by the defenders minus the IPC
+/// lost by the attackers. A positive swing favours the attackers.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct TuvSwing<TProb = Probability> {
+    /// The swing, over the outcomes which weren't pruned.
+    pub swing: Stat,
+    /// The distribution of the swing, sorted in ascending order.
+    pub swing_dist: ProbDist<i64, TProb>,
+    /// The covariance of the IPC lost by the attackers and the IPC lost by the defenders, over
+    /// the outcomes which weren't pruned.
+    pub ipc_lost_covariance: f64,
+}
+
+impl<TProb: ProbValue> TuvSwing<TProb> {
+    /// Constructs the swing from the distribution of IPC lost by the attackers and defenders.
+    pub fn from_ipc_lost(ipc_lost: &ProbDist<(u32, u32), TProb>) -> Self {
+        let mut swing = Stat::default();
+        let mut swing_dist = ProbDistBuilder::with_capacity(ipc_lost.len());
+        let mut attacker = Stat::default();
+        let mut defender = Stat::default();
+        let mut total_p = TProb::zero();
+        for outcome in ipc_lost.outcomes() {
+            let (attacker_lost, defender_lost) = outcome.item;
+            let value = defender_lost as i64 - attacker_lost as i64;
+            total_p += outcome.p.clone();
+            swing.add_value(value as f64, &outcome.p, &total_p);
+            swing_dist.add(value, outcome.p.clone());
+            attacker.add_value(attacker_lost, &outcome.p, &total_p);
+            defender.add_value(defender_lost, &outcome.p, &total_p);
+        }
+
+        let ipc_lost_covariance = if total_p == TProb::zero() {
+            0.0
+        } else {
+            ipc_lost
+                .outcomes()
+                .iter()
+                .map(|outcome| {
+                    let (attacker_lost, defender_lost) = outcome.item;
+                    outcome.p.to_f64()
+                        * (attacker_lost as f64 - attacker.mean)
+                        * (defender_lost as f64 - defender.mean)
+                })
+                .sum::<f64>()
+                / total_p.to_f64()
+        };
+        let mut swing_dist = swing_dist.build();
+        swing_dist.sort();
+        Self {
+            swing: swing.normalized(&total_p),
+            swing_dist,
+            ipc_lost_covariance,
+        }
+    }
+
+    /// Divides the probabilities of the swing distribution by `divisor`. The swing and the
+    /// covariance only cover the outcomes which weren't pruned, so they're kept as is.
+    pub(crate) fn divide(&self, divisor: &TProb) -> Self {
+        TuvSwing {
+            swing_dist: self.swing_dist.divide(divisor),
+            ..self.clone()
+        }
+    }
+
+    /// The probability of the swing favouring the attackers.
+    pub fn positive_p(&self) -> TProb {
+        TProb::one() - self.swing_dist.cdf(&0)
+    }
+}
@@ -0,0 +1,51 @@
+use super::*;
+use crate::*;
+
+/// The survival of a single type of unit on one side of a battle.
+#[cfg_attr(feature = "serde1", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnitSurvival<TProb = Probability> {
+    /// The number of surviving units.
+    pub count: Stat,
+    /// The distribution of the number of surviving units, sorted in ascending order.
+    pub count_dist: ProbDist<u32, TProb>,
+}
+
+impl<TProb: ProbValue> UnitSurvival<TProb> {
+    /// Constructs the survival of `unit` on the given side from the combats the battle ended with.
+    pub fn from_combats<'a, TBattlePhase, TUnit, I>(combats: I, unit: &TUnit, side: Side) -> Self
+    where
+        TBattlePhase: BattlePhase + 'a,
+        TUnit: Unit + 'a,
+        I: IntoIterator<Item = &'a Prob<Combat<TBattlePhase, TUnit>, TProb>>,
+    {
+        let mut count = Stat::default();
+        let mut count_dist = ProbDistBuilder::new();
+        let mut total_p = TProb::zero();
+        for combat in combats {
+            let survivors = CombatContext::from_combat(&combat.item, side)
+                .friendlies()
+                .count(unit);
+            total_p += combat.p.clone();
+            count.add_value(survivors, &combat.p, &total_p);
+            count_dist.add(survivors, combat.p.clone());
+        }
+
+        let mut count_dist = count_dist.build();
+        count_dist.sort();
+        Self { count, count_dist }
+    }
+
+    /// The probability that at least `count` units survive.
+    pub fn at_least_p(&self, count: u32) -> TProb {
+        if count == 0 {
+            return TProb::one();
+        }
+        TProb::one() - self.count_dist.cdf(&(count - 1))
+    }
+
+    /// The probability that any units survive.
+    pub fn survival_p(&self) -> TProb {
+        self.at_least_p(1)
+    }
+}
@@ -1,15 +1,29 @@
-use crate::{Force, Hit, ProbDist, QuantDist, Unit};
+use crate::{
+    BattlePhase, CombatContext, Force, Hit, ProbDist, ProbValue, Probability, QuantDist, Unit,
+};
 
 /// Selects surviors given a starting force and a distribution of hits.
-pub trait SurvivorSelector<TUnit, THit>
+pub trait SurvivorSelector<TUnit, THit, TProb = Probability>
 where
     TUnit: Unit,
     THit: Hit<TUnit>,
+    TProb: ProbValue,
 {
     /// Returns all possible surviving forces and their probabilities.
     fn select(
         &self,
         starting_force: &QuantDist<TUnit>,
-        outcomes: &ProbDist<QuantDist<THit>>,
-    ) -> ProbDist<Force<TUnit>>;
+        outcomes: &ProbDist<QuantDist<THit>, TProb>,
+    ) -> ProbDist<Force<TUnit>, TProb>;
+
+    /// Returns all possible surviving forces of the side represented by `context`, and their
+    /// probabilities, taking the rest of the combat into account. Defaults to `select` with the
+    /// friendly force, ignoring the hostiles and the battle phase.
+    fn select_in<TBattlePhase: BattlePhase>(
+        &self,
+        context: &CombatContext<TBattlePhase, TUnit>,
+        outcomes: &ProbDist<QuantDist<THit>, TProb>,
+    ) -> ProbDist<Force<TUnit>, TProb> {
+        self.select(context.friendlies(), outcomes)
+    }
 }
@@ -1,5 +1,5 @@
 use crate::{QuantDist, Side};
-use std::{fmt::Debug, hash::Hash, rc::Rc};
+use std::{fmt::Debug, hash::Hash, sync::Arc};
 
 /// Represents a unit.
 pub trait Unit: Debug + Clone + Copy + Eq + Ord + Hash + Sized + core::fmt::Display {
@@ -14,6 +14,13 @@ pub trait Unit: Debug + Clone + Copy + Eq + Ord + Hash + Sized + core::fmt::Disp
 
     /// Returns the defense strength of this unit.
     fn defense(self) -> u8;
+
+    /// Returns whether or not this unit can capture the territory once the defenders are
+    /// destroyed. Air and naval units, for example, can't take a land territory. Defaults to
+    /// `true`.
+    fn can_capture(self) -> bool {
+        true
+    }
 }
 
-pub type Force<Unit> = Rc<QuantDist<Unit>>;
+pub type Force<Unit> = Arc<QuantDist<Unit>>;
@@ -1,7 +1,6 @@
 use aa1942_2e::Unit as Unit1942_2E;
 use calc::stats::*;
 use calc::*;
-use std::convert::TryInto;
 
 fn main() {
     let attackers = Force::new(
@@ -30,7 +29,7 @@ fn main() {
 
     let sequence = aa1942_2e::BattlePhase::create_sequence(&attackers, &defenders);
     let mut round_manager = aa1942_2e::create_round_manager(attackers, defenders);
-    round_manager.set_prune_threshold(0.0000000001.try_into().unwrap());
+    round_manager.set_prune_threshold(Probability::new(0.0000000001));
     let mut summarizer = Summarizer::new(round_manager.last_round());
 
     println!("Round {} - {}", 0, sequence.combat_at(0));
@@ -51,14 +50,14 @@ fn main() {
         const PROCESS_LIMIT: usize = 5000;
         let mut processor = round_manager.round_processor();
 
-        while !processor.process(PROCESS_LIMIT) {
+        while !processor.process_parallel(PROCESS_LIMIT) {
             println!(
                 " {:5.2}% complete",
                 processor.processed_outcomes() as f64 / processor.total_outcomes() as f64 * 100.0
             );
         }
 
-        let last_round = processor.finish();
+        let last_round = processor.finish_parallel();
         let summary = summarizer.add_round(last_round);
 
         println!("Attacker Stats:");
@@ -94,15 +93,17 @@ fn main() {
     );
     println!("Winner      Prob.");
     println!("Attack:    {:>5.2}%", summary.attacker.win_p * 100.0);
+    println!("Capture:   {:>5.2}%", summary.capture_p * 100.0);
     println!("Defend:    {:>5.2}%", summary.defender.win_p * 100.0);
     println!("Draw:      {:>5.2}%", summary.draw_p * 100.0);
-    if round_manager.last_round().stalemate {
-        println!(
-            "Stalemate: {:>5.2}%",
-            round_manager.last_round().total_probability() * 100.0
-        );
+    if summary.stalemate_p > Probability::zero() {
+        println!("Stalemate: {:>5.2}%", summary.stalemate_p * 100.0);
     }
     println!("Total:     {:>8.5}%", summary.total_p * 100.0);
+    println!(
+        "Expected length: {:.2} rounds",
+        summary.expected_length(&sequence)
+    );
 
     println!(
         "Attacker Loss - μ: {:>6.2} IPC, σ: {:>5.2} IPC",
@@ -114,6 +115,24 @@ fn main() {
         summary.prebattle.defender.ipc.mean - summary.defender.ipc.mean,
         summary.defender.ipc.std_dev(),
     );
+    println!(
+        "TUV Swing     - μ: {:>6.2} IPC, σ: {:>5.2} IPC, P(> 0): {:>5.2}%",
+        summary.tuv_swing.swing.mean,
+        summary.tuv_swing.swing.std_dev(),
+        summary.tuv_swing.positive_p() * 100.0,
+    );
+    let median = Probability::from_ratio(1, 2);
+    let ninetieth = Probability::from_ratio(9, 10);
+    println!(
+        "Attacker Loss - median: {:>4} IPC, 90th percentile: {:>4} IPC",
+        summary.attacker.ipc_lost_percentile(&median),
+        summary.attacker.ipc_lost_percentile(&ninetieth),
+    );
+    println!(
+        "Defender Loss - median: {:>4} IPC, 90th percentile: {:>4} IPC",
+        summary.defender.ipc_lost_percentile(&median),
+        summary.defender.ipc_lost_percentile(&ninetieth),
+    );
 }
 
 fn print_round_side_summary(summary: &RoundSideSummary) {
@@ -2,7 +2,11 @@ mod utils;
 use wasm_bindgen::prelude::*;
 
 use calc::stats::*;
-use calc::{Force, QuantDistBuilder, Unit};
+use calc::{
+    AdaptiveThreshold, CheckpointVersion, Force, MassBudget, Pruner, PruningStrategy,
+    QuantDistBuilder, RoundManagerCheckpoint, SummarizerCheckpoint, TopK, Unit,
+};
+use core::convert::TryInto;
 
 #[cfg(feature = "wee_alloc")]
 #[global_allocator]
@@ -32,12 +36,26 @@ export interface RoundSideSummary {
     win_p: Probability;
 }
 
+export interface Casualties {
+    attackers: Record<string, number>;
+    defenders: Record<string, number>;
+}
+
+export interface Luck {
+    swing: number;
+    percentile: Probability;
+    at_least_p: Probability;
+}
+
 export interface RoundSummary {
     index: number;
     attacker: RoundSideSummary;
     defender: RoundSideSummary;
     draw_p: Probability;
+    retreat_p: Probability;
+    stalemate_p: Probability;
     pruned_p: Probability;
+    casualties: Record<string, Casualties>;
 }
 
 "#;
@@ -50,14 +68,65 @@ type RoundManagerAA1942_2E = calc::RoundManager<
     aa1942_2e::RollSelector,
     aa1942_2e::SurvivorSelector,
 >;
+type CombatManagerAA1942_2E = calc::CombatManager<
+    aa1942_2e::BattlePhase,
+    Unit1942_2E,
+    aa1942_2e::Hit,
+    aa1942_2e::RollSelector,
+    aa1942_2e::SurvivorSelector,
+>;
 type PhaseSequenceAA1942_2E = calc::PhaseSequence<aa1942_2e::BattlePhase>;
 
+/// A saved battle, from which the calculation can be resumed.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct BattleCheckpoint {
+    round_manager: RoundManagerCheckpoint<aa1942_2e::BattlePhase, Unit1942_2E>,
+    summarizer: SummarizerCheckpoint<aa1942_2e::BattlePhase, Unit1942_2E>,
+}
+
+/// The versions of a saved battle, which are checked before the rest of it is deserialized.
+#[derive(serde::Deserialize)]
+struct BattleCheckpointVersions {
+    round_manager: CheckpointVersion,
+    summarizer: CheckpointVersion,
+}
+
+/// Indicates whether or not both forces hold the same number of each unit.
+fn same_units(first: &Force<Unit1942_2E>, second: &Force<Unit1942_2E>) -> bool {
+    Unit1942_2E::all()
+        .iter()
+        .all(|unit| first.count(unit) == second.count(unit))
+}
+
 #[wasm_bindgen]
 #[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub enum Ruleset {
     AA1942_2E,
 }
 
+/// A named strategy for choosing casualties.
+#[wasm_bindgen]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Eq, Ord, Hash)]
+pub enum CasualtyProfile {
+    Standard,
+    CheapestFirst,
+    LowestStrengthFirst,
+    PreserveAttack,
+    PreserveDefense,
+}
+
+impl From<CasualtyProfile> for aa1942_2e::CasualtyProfile {
+    fn from(profile: CasualtyProfile) -> Self {
+        match profile {
+            CasualtyProfile::Standard => aa1942_2e::CasualtyProfile::Standard,
+            CasualtyProfile::CheapestFirst => aa1942_2e::CasualtyProfile::CheapestFirst,
+            CasualtyProfile::LowestStrengthFirst => aa1942_2e::CasualtyProfile::LowestStrengthFirst,
+            CasualtyProfile::PreserveAttack => aa1942_2e::CasualtyProfile::PreserveAttack,
+            CasualtyProfile::PreserveDefense => aa1942_2e::CasualtyProfile::PreserveDefense,
+        }
+    }
+}
+
 #[wasm_bindgen]
 pub struct UnitProvider {
     ruleset: Ruleset,
@@ -111,6 +180,13 @@ pub struct BattleBuilder {
     ruleset: Ruleset,
     attackers: QuantDistBuilder<Unit1942_2E>,
     defenders: QuantDistBuilder<Unit1942_2E>,
+    low_luck: bool,
+    low_luck_anti_air: bool,
+    attacker_profile: CasualtyProfile,
+    defender_profile: CasualtyProfile,
+    attacker_reserved: Vec<aa1942_2e::Reservation>,
+    defender_reserved: Vec<aa1942_2e::Reservation>,
+    pruning_strategy: Box<dyn PruningStrategy>,
 }
 
 #[wasm_bindgen]
@@ -121,6 +197,87 @@ impl BattleBuilder {
             ruleset,
             attackers: QuantDistBuilder::default(),
             defenders: QuantDistBuilder::default(),
+            low_luck: false,
+            low_luck_anti_air: false,
+            attacker_profile: CasualtyProfile::Standard,
+            defender_profile: CasualtyProfile::Standard,
+            attacker_reserved: aa1942_2e::SurvivorSelector::default_attacker_reservations(),
+            defender_reserved: Vec::new(),
+            pruning_strategy: Box::new(Pruner::new(0.0000000001.try_into().unwrap())),
+        }
+    }
+
+    #[wasm_bindgen(js_name = setLowLuck)]
+    pub fn set_low_luck(&mut self, low_luck: bool, include_anti_air: bool) {
+        self.low_luck = low_luck;
+        self.low_luck_anti_air = include_anti_air;
+    }
+
+    #[wasm_bindgen(js_name = setPruneThreshold)]
+    pub fn set_prune_threshold(&mut self, threshold: f64) -> Result<(), JsValue> {
+        let threshold = threshold.try_into().map_err(JsValue::from_str)?;
+        self.pruning_strategy = Box::new(Pruner::new(threshold));
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setPruneTopK)]
+    pub fn set_prune_top_k(&mut self, k: u32) {
+        self.pruning_strategy = Box::new(TopK { k: k as usize });
+    }
+
+    #[wasm_bindgen(js_name = setPruneMassBudget)]
+    pub fn set_prune_mass_budget(&mut self, budget: f64) -> Result<(), JsValue> {
+        let budget = budget.try_into().map_err(JsValue::from_str)?;
+        self.pruning_strategy = Box::new(MassBudget { budget });
+        Ok(())
+    }
+
+    #[wasm_bindgen(js_name = setAdaptivePruning)]
+    pub fn set_adaptive_pruning(
+        &mut self,
+        threshold: f64,
+        max_pending: u32,
+    ) -> Result<(), JsValue> {
+        let threshold = threshold.try_into().map_err(JsValue::from_str)?;
+        self.pruning_strategy = Box::new(AdaptiveThreshold::new(threshold, max_pending as usize));
+        Ok(())
+    }
+
+    /// Sets the order in which the side removes its casualties.
+    #[wasm_bindgen(js_name = setCasualtyProfile)]
+    pub fn set_casualty_profile(&mut self, attacker: bool, profile: CasualtyProfile) {
+        if attacker {
+            self.attacker_profile = profile;
+        } else {
+            self.defender_profile = profile;
+        }
+    }
+
+    /// Keeps `count` of the unit at `unit_index` for as long as the side can take other
+    /// casualties. Once it can't, its reservations are released starting with the lowest
+    /// priority. By default, the attacker reserves a single tank.
+    #[wasm_bindgen(js_name = addReservation)]
+    pub fn add_reservation(&mut self, attacker: bool, unit_index: u32, count: u32, priority: u32) {
+        match self.ruleset {
+            Ruleset::AA1942_2E => {
+                let unit = Unit1942_2E::all()[unit_index as usize];
+                let reservation = aa1942_2e::Reservation::new(unit, count, priority);
+                if attacker {
+                    self.attacker_reserved.push(reservation);
+                } else {
+                    self.defender_reserved.push(reservation);
+                }
+            }
+        }
+    }
+
+    /// Removes every reservation of the side, including the default ones.
+    #[wasm_bindgen(js_name = clearReservations)]
+    pub fn clear_reservations(&mut self, attacker: bool) {
+        if attacker {
+            self.attacker_reserved.clear();
+        } else {
+            self.defender_reserved.clear();
         }
     }
 
@@ -145,12 +302,82 @@ impl BattleBuilder {
     }
 
     pub fn build(self) -> Battle {
-        use std::rc::Rc;
+        let combat_manager = self.combat_manager();
         Battle::new(
-            Rc::new(self.attackers.build()),
-            Rc::new(self.defenders.build()),
+            Force::new(self.attackers.build()),
+            Force::new(self.defenders.build()),
+            combat_manager,
+            self.pruning_strategy,
         )
     }
+
+    /// Resumes a battle saved with `Battle.checkpoint`, which must have been built with the same
+    /// units, dice settings, casualty profiles and reservations as this builder. The pruning
+    /// settings are taken from the checkpoint. Fails if the checkpoint was saved in another
+    /// version of the format, or with different units.
+    #[allow(deprecated)]
+    pub fn resume(self, checkpoint: JsValue) -> Result<Battle, JsValue> {
+        let versions: BattleCheckpointVersions = checkpoint
+            .into_serde()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        versions
+            .round_manager
+            .check()
+            .and(versions.summarizer.check())
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let checkpoint: BattleCheckpoint = checkpoint
+            .into_serde()
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+
+        let combat_manager = self.combat_manager();
+        let attackers = Force::new(self.attackers.build());
+        let defenders = Force::new(self.defenders.build());
+        let prebattle = checkpoint.summarizer.summarizer.prebattle_combat();
+        if !same_units(&prebattle.attackers, &attackers)
+            || !same_units(&prebattle.defenders, &defenders)
+        {
+            return Err(JsValue::from_str(
+                "The checkpoint was saved with different units than this battle.",
+            ));
+        }
+        let sequence = aa1942_2e::BattlePhase::create_sequence(&attackers, &defenders);
+        let round_manager =
+            calc::RoundManager::resume(combat_manager, sequence.clone(), checkpoint.round_manager)
+                .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        let summarizer = Summarizer::resume(checkpoint.summarizer)
+            .map_err(|e| JsValue::from_str(&e.to_string()))?;
+        Ok(Battle {
+            round_manager,
+            sequence,
+            summarizer,
+        })
+    }
+
+    fn combat_manager(&self) -> CombatManagerAA1942_2E {
+        let attacker_survivor_selector = aa1942_2e::SurvivorSelector::from_profile(
+            self.attacker_profile.into(),
+            calc::Side::Attacker,
+            self.attacker_reserved.clone(),
+        );
+        let defender_survivor_selector = aa1942_2e::SurvivorSelector::from_profile(
+            self.defender_profile.into(),
+            calc::Side::Defender,
+            self.defender_reserved.clone(),
+        );
+        aa1942_2e::get_combat_manager_selecting(
+            self.roll_selector(),
+            attacker_survivor_selector,
+            defender_survivor_selector,
+        )
+    }
+
+    fn roll_selector(&self) -> aa1942_2e::RollSelector {
+        if self.low_luck {
+            aa1942_2e::RollSelector::low_luck(self.low_luck_anti_air)
+        } else {
+            aa1942_2e::RollSelector::default()
+        }
+    }
 }
 
 #[wasm_bindgen]
@@ -162,11 +389,16 @@ pub struct Battle {
 
 #[wasm_bindgen]
 impl Battle {
-    fn new(attackers: Force<Unit1942_2E>, defenders: Force<Unit1942_2E>) -> Self {
-        use core::convert::TryInto;
+    fn new(
+        attackers: Force<Unit1942_2E>,
+        defenders: Force<Unit1942_2E>,
+        combat_manager: CombatManagerAA1942_2E,
+        pruning_strategy: Box<dyn PruningStrategy>,
+    ) -> Self {
         let sequence = aa1942_2e::BattlePhase::create_sequence(&attackers, &defenders);
-        let mut round_manager = aa1942_2e::create_round_manager(attackers, defenders);
-        round_manager.set_prune_threshold(0.0000000001.try_into().unwrap());
+        let mut round_manager =
+            calc::RoundManager::new(combat_manager, sequence.clone(), attackers, defenders);
+        round_manager.set_pruning_strategy(pruning_strategy);
         let summarizer = Summarizer::new(round_manager.last_round());
         Self {
             round_manager,
@@ -175,6 +407,16 @@ impl Battle {
         }
     }
 
+    /// Saves the battle so far, so that it can be resumed with `BattleBuilder.resume`.
+    #[allow(deprecated)]
+    pub fn checkpoint(&self) -> JsValue {
+        let checkpoint = BattleCheckpoint {
+            round_manager: self.round_manager.checkpoint(),
+            summarizer: self.summarizer.checkpoint(),
+        };
+        JsValue::from_serde(&checkpoint).unwrap_throw()
+    }
+
     #[wasm_bindgen(js_name = isComplete)]
     pub fn is_complete(&self) -> bool {
         self.round_manager.is_complete()
@@ -194,6 +436,7 @@ impl Battle {
     }
 
     #[wasm_bindgen(js_name = roundSummaries)]
+    #[allow(deprecated)]
     pub fn round_summaries(&self) -> JsValue {
         let summary = self.summarizer.clone().summarize();
         let mut summaries = summary.round_summaries;
@@ -201,6 +444,29 @@ impl Battle {
         JsValue::from_serde(&summaries).unwrap_throw()
     }
 
+    #[wasm_bindgen(js_name = gameRoundSummaries)]
+    #[allow(deprecated)]
+    pub fn game_round_summaries(&self) -> JsValue {
+        let summary = self.summarizer.clone().summarize();
+        let mut summaries = summary.game_round_summaries(&self.sequence);
+        summaries.insert(0, summary.prebattle);
+        JsValue::from_serde(&summaries).unwrap_throw()
+    }
+
+    /// The expected casualties of each side over the battle so far, keyed by battle phase.
+    #[wasm_bindgen(js_name = phaseCasualties)]
+    #[allow(deprecated)]
+    pub fn phase_casualties(&self) -> JsValue {
+        let summary = self.summarizer.clone().summarize();
+        JsValue::from_serde(&summary.casualties).unwrap_throw()
+    }
+
+    #[wasm_bindgen(js_name = expectedLength)]
+    pub fn expected_length(&self) -> f64 {
+        let summary = self.summarizer.clone().summarize();
+        summary.expected_length(&self.sequence)
+    }
+
     #[wasm_bindgen(js_name = roundStats)]
     pub fn round_stats(&self) -> RoundStats {
         let round_manager = &self.round_manager;
@@ -218,6 +484,84 @@ impl Battle {
         }
     }
 
+    /// The probability of the given side losing more than `ipc` IPC.
+    #[wasm_bindgen(js_name = ipcLostOverP)]
+    pub fn ipc_lost_over_p(&self, attacker: bool, ipc: u32) -> f64 {
+        let summary = self.summarizer.clone().summarize();
+        let side = if attacker {
+            &summary.attacker
+        } else {
+            &summary.defender
+        };
+        side.ipc_lost_over_p(ipc).into()
+    }
+
+    /// The amount of IPC which the given side loses no more than with a probability of `q`.
+    #[wasm_bindgen(js_name = ipcLostPercentile)]
+    pub fn ipc_lost_percentile(&self, attacker: bool, q: f64) -> Result<u32, JsValue> {
+        let q = q.try_into().map_err(JsValue::from_str)?;
+        let summary = self.summarizer.clone().summarize();
+        let side = if attacker {
+            &summary.attacker
+        } else {
+            &summary.defender
+        };
+        Ok(side.ipc_lost_percentile(&q))
+    }
+
+    /// How lucky the given side was for the battle to end with the observed survivors, where
+    /// each array holds the count of each unit type, in the order of `Unit::all`.
+    #[wasm_bindgen(js_name = luck)]
+    #[allow(deprecated)]
+    pub fn luck(
+        &self,
+        attacker: bool,
+        surviving_attackers: Vec<u32>,
+        surviving_defenders: Vec<u32>,
+    ) -> JsValue {
+        let summary = self.summarizer.clone().summarize();
+        let force = |counts: Vec<u32>| {
+            let mut builder = QuantDistBuilder::new();
+            for (unit, count) in Unit1942_2E::all().iter().zip(counts) {
+                builder.add(*unit, count);
+            }
+            Force::new(builder.build())
+        };
+        let combat = calc::Combat {
+            battle_phase: aa1942_2e::BattlePhase::General,
+            attackers: force(surviving_attackers),
+            defenders: force(surviving_defenders),
+        };
+        let side = if attacker {
+            calc::Side::Attacker
+        } else {
+            calc::Side::Defender
+        };
+        JsValue::from_serde(&summary.luck(&combat, side)).unwrap_throw()
+    }
+
+    /// The probability that any units of the given type survive on the given side.
+    #[wasm_bindgen(js_name = unitSurvivalP)]
+    pub fn unit_survival_p(&self, attacker: bool, unit_index: u32) -> f64 {
+        self.unit_survival(attacker, unit_index).survival_p().into()
+    }
+
+    /// The expected number of surviving units of the given type on the given side.
+    #[wasm_bindgen(js_name = unitSurvivorMean)]
+    pub fn unit_survivor_mean(&self, attacker: bool, unit_index: u32) -> f64 {
+        self.unit_survival(attacker, unit_index).count.mean
+    }
+
+    fn unit_survival(&self, attacker: bool, unit_index: u32) -> UnitSurvival {
+        let summary = self.summarizer.clone().summarize();
+        let side = if attacker {
+            calc::Side::Attacker
+        } else {
+            calc::Side::Defender
+        };
+        summary.unit_survival(side, &Unit1942_2E::all()[unit_index as usize])
+    }
+
     #[wasm_bindgen(js_name = cumulativeStats)]
     pub fn cumulative_stats(&self) -> CumulativeStats {
         let summary = self.summarizer.clone().summarize();
@@ -225,6 +569,14 @@ impl Battle {
             attacker_win_p: summary.attacker.win_p.into(),
             defender_win_p: summary.defender.win_p.into(),
             draw_p: summary.draw_p.into(),
+            stalemate_p: summary.stalemate_p.into(),
+            capture_p: summary.capture_p.into(),
+            tuv_swing: summary.tuv_swing.swing.mean,
+            tuv_swing_stddev: summary.tuv_swing.swing.std_dev(),
+            tuv_swing_positive_p: summary.tuv_swing.positive_p().into(),
+            attacker_win_upper_p: summary.attacker_win_bounds().upper.into(),
+            defender_win_upper_p: summary.defender_win_bounds().upper.into(),
+            draw_upper_p: summary.draw_bounds().upper.into(),
             attacker_ipc_lost: summary.attacker.ipc_lost.mean,
             defender_ipc_lost: summary.defender.ipc_lost.mean,
             attacker_ipc_stddev: summary.attacker.ipc.std_dev(),
@@ -236,9 +588,10 @@ impl Battle {
     pub fn advance_round(&mut self) {
         let round_manager = &mut self.round_manager;
         let round = round_manager.advance_round();
-        self.summarizer.add_round(&round);
+        self.summarizer.add_round(round);
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Default::default()
     }
@@ -246,9 +599,7 @@ impl Battle {
 
 impl Default for Battle {
     fn default() -> Self {
-        let attackers = Force::new(vec![].into());
-        let defenders = Force::new(vec![].into());
-        Self::new(attackers, defenders)
+        BattleBuilder::new(Ruleset::AA1942_2E).build()
     }
 }
 
@@ -306,6 +657,14 @@ pub struct CumulativeStats {
     defender_win_p: f64,
     attacker_win_p: f64,
     draw_p: f64,
+    stalemate_p: f64,
+    capture_p: f64,
+    tuv_swing: f64,
+    tuv_swing_stddev: f64,
+    tuv_swing_positive_p: f64,
+    attacker_win_upper_p: f64,
+    defender_win_upper_p: f64,
+    draw_upper_p: f64,
     pruned_p: f64,
     attacker_ipc_lost: f64,
     defender_ipc_lost: f64,
@@ -330,6 +689,46 @@ impl CumulativeStats {
         self.draw_p
     }
 
+    #[wasm_bindgen(getter = stalemateP)]
+    pub fn stalemate_p(&self) -> f64 {
+        self.stalemate_p
+    }
+
+    #[wasm_bindgen(getter = captureP)]
+    pub fn capture_p(&self) -> f64 {
+        self.capture_p
+    }
+
+    #[wasm_bindgen(getter = tuvSwing)]
+    pub fn tuv_swing(&self) -> f64 {
+        self.tuv_swing
+    }
+
+    #[wasm_bindgen(getter = tuvSwingStdDev)]
+    pub fn tuv_swing_stddev(&self) -> f64 {
+        self.tuv_swing_stddev
+    }
+
+    #[wasm_bindgen(getter = tuvSwingPositiveP)]
+    pub fn tuv_swing_positive_p(&self) -> f64 {
+        self.tuv_swing_positive_p
+    }
+
+    #[wasm_bindgen(getter = attackerWinUpperP)]
+    pub fn attacker_win_upper_p(&self) -> f64 {
+        self.attacker_win_upper_p
+    }
+
+    #[wasm_bindgen(getter = defenderWinUpperP)]
+    pub fn defender_win_upper_p(&self) -> f64 {
+        self.defender_win_upper_p
+    }
+
+    #[wasm_bindgen(getter = drawUpperP)]
+    pub fn draw_upper_p(&self) -> f64 {
+        self.draw_upper_p
+    }
+
     #[wasm_bindgen(getter = prunedP)]
     pub fn pruned_p(&self) -> f64 {
         self.pruned_p